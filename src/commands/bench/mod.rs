@@ -0,0 +1,106 @@
+mod stats;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc};
+use std::thread;
+use std::time::Instant;
+
+use crate::commands::build::BuildOptions;
+use crate::commands::publish::preview::{self, PreviewRequest, Target};
+use crate::http;
+use crate::settings::project::Project;
+use crate::terminal::message;
+
+use stats::{Sample, Stats};
+
+// How many requests are sent, and where.
+#[derive(Debug)]
+pub struct BenchOptions {
+    pub requests: usize,
+    pub concurrency: usize,
+    // Send the requests to the URL of the request itself, rather than to a
+    // preview of the project
+    pub deployed: bool,
+    // Print the results as JSON on stdout, and everything else on stderr
+    pub json: bool,
+}
+
+// Sends the request to the preview of the project, or to a deployed worker,
+// as many times as asked with that many requests in flight, and reports the
+// latencies and statuses of the responses.
+pub fn bench(
+    project: &Project,
+    request: PreviewRequest,
+    options: &BenchOptions,
+    build: &BuildOptions,
+) -> Result<(), failure::Error> {
+    if options.json {
+        message::to_stderr(true);
+    }
+    if options.requests == 0 || options.concurrency == 0 {
+        failure::bail!("The number of requests and the concurrency must be at least 1");
+    }
+
+    let client = http::proxy_client();
+    let target = if options.deployed {
+        // the request URL is already the full URL of the deployed worker
+        Target::Url(request.url.join("/")?)
+    } else {
+        Target::preview(preview::upload(&client, project, None, build)?)
+    };
+    let concurrency = options.concurrency.min(options.requests);
+    message::working(&format!(
+        "Sending {} {} {} requests, {} at a time",
+        options.requests, request.method, request.url, concurrency
+    ));
+
+    let target = Arc::new(target);
+    let request = Arc::new(request);
+    let remaining = Arc::new(AtomicUsize::new(options.requests));
+    let (samples, received) = mpsc::channel();
+
+    let start = Instant::now();
+    let workers: Vec<_> = (0..concurrency)
+        .map(|_| {
+            let (client, target, request) = (client.clone(), target.clone(), request.clone());
+            let (remaining, samples) = (remaining.clone(), samples.clone());
+            thread::spawn(move || {
+                // claim requests until there are none left
+                while remaining
+                    .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+                    .is_ok()
+                {
+                    let sent = Instant::now();
+                    let status = target
+                        .send(&client, &request)
+                        .map(|response| response.status.as_u16())
+                        .map_err(|e| e.to_string());
+                    let sample = Sample {
+                        status,
+                        elapsed: sent.elapsed(),
+                    };
+                    if samples.send(sample).is_err() {
+                        return;
+                    }
+                }
+            })
+        })
+        .collect();
+    drop(samples);
+
+    let samples: Vec<Sample> = received.iter().collect();
+    for worker in workers {
+        if worker.join().is_err() {
+            failure::bail!("A benchmark thread panicked");
+        }
+    }
+    let stats = Stats::new(&samples, concurrency, start.elapsed());
+
+    if options.json {
+        println!("{}", serde_json::to_string_pretty(&stats)?);
+    } else {
+        message::detail(&stats.render());
+    }
+
+    Ok(())
+}
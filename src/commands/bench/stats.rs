@@ -0,0 +1,191 @@
+use std::collections::BTreeMap;
+use std::time::Duration;
+
+use serde::Serialize;
+
+// The outcome of one request: the status it got, or the error that kept it
+// from getting one.
+#[derive(Debug)]
+pub struct Sample {
+    pub status: Result<u16, String>,
+    pub elapsed: Duration,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct Latency {
+    pub min: f64,
+    pub mean: f64,
+    pub p50: f64,
+    pub p90: f64,
+    pub p95: f64,
+    pub p99: f64,
+    pub max: f64,
+}
+
+// What a run of requests adds up to. Latencies are in milliseconds, and only
+// count requests that got a response; errors are requests that did not, or
+// that got a 5xx status.
+#[derive(Debug, Serialize)]
+pub struct Stats {
+    pub requests: usize,
+    pub concurrency: usize,
+    pub duration_ms: f64,
+    pub requests_per_second: f64,
+    pub latency_ms: Option<Latency>,
+    pub statuses: BTreeMap<u16, usize>,
+    pub errors: usize,
+    pub error_rate: f64,
+    // The distinct transport errors, with how often each happened
+    pub failures: BTreeMap<String, usize>,
+}
+
+impl Stats {
+    pub fn new(samples: &[Sample], concurrency: usize, duration: Duration) -> Stats {
+        let mut statuses = BTreeMap::new();
+        let mut failures = BTreeMap::new();
+        let mut latencies = vec![];
+        for sample in samples {
+            match &sample.status {
+                Ok(status) => {
+                    *statuses.entry(*status).or_insert(0) += 1;
+                    latencies.push(millis(sample.elapsed));
+                }
+                Err(e) => *failures.entry(e.clone()).or_insert(0) += 1,
+            }
+        }
+
+        let server_errors: usize = statuses
+            .iter()
+            .filter(|(status, _)| **status >= 500)
+            .map(|(_, count)| count)
+            .sum();
+        let errors = server_errors + failures.values().sum::<usize>();
+        let requests = samples.len();
+        let duration_ms = millis(duration);
+
+        Stats {
+            requests,
+            concurrency,
+            duration_ms,
+            requests_per_second: if duration_ms > 0.0 {
+                requests as f64 * 1000.0 / duration_ms
+            } else {
+                0.0
+            },
+            latency_ms: Latency::new(latencies),
+            statuses,
+            errors,
+            error_rate: if requests > 0 {
+                errors as f64 / requests as f64
+            } else {
+                0.0
+            },
+            failures,
+        }
+    }
+
+    // The report for the terminal
+    pub fn render(&self) -> String {
+        let mut lines = vec![format!(
+            "{} requests, {} at a time, in {:.0} ms: {:.1} requests per second",
+            self.requests, self.concurrency, self.duration_ms, self.requests_per_second
+        )];
+        if let Some(l) = &self.latency_ms {
+            lines.push(format!(
+                "Latency (ms): min {:.1}, mean {:.1}, p50 {:.1}, p90 {:.1}, p95 {:.1}, p99 {:.1}, max {:.1}",
+                l.min, l.mean, l.p50, l.p90, l.p95, l.p99, l.max
+            ));
+        }
+        for (status, count) in &self.statuses {
+            lines.push(format!("  {}: {} responses", status, count));
+        }
+        for (failure, count) in &self.failures {
+            lines.push(format!("  failed: {} ({} times)", failure, count));
+        }
+        lines.push(format!(
+            "Errors: {} ({:.1}%)",
+            self.errors,
+            self.error_rate * 100.0
+        ));
+        lines.join("\n")
+    }
+}
+
+impl Latency {
+    fn new(mut latencies: Vec<f64>) -> Option<Latency> {
+        if latencies.is_empty() {
+            return None;
+        }
+        latencies.sort_by(|a, b| a.partial_cmp(b).expect("latencies are never NaN"));
+
+        Some(Latency {
+            min: latencies[0],
+            mean: latencies.iter().sum::<f64>() / latencies.len() as f64,
+            p50: percentile(&latencies, 50.0),
+            p90: percentile(&latencies, 90.0),
+            p95: percentile(&latencies, 95.0),
+            p99: percentile(&latencies, 99.0),
+            max: latencies[latencies.len() - 1],
+        })
+    }
+}
+
+// The nearest-rank percentile `p` of `sorted`
+fn percentile(sorted: &[f64], p: f64) -> f64 {
+    let rank = (p / 100.0 * sorted.len() as f64).ceil() as usize;
+    sorted[rank.max(1) - 1]
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs() as f64 * 1000.0 + f64::from(duration.subsec_nanos()) / 1_000_000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(status: Result<u16, &str>, ms: u64) -> Sample {
+        Sample {
+            status: status.map_err(|e| e.to_string()),
+            elapsed: Duration::from_millis(ms),
+        }
+    }
+
+    #[test]
+    fn it_computes_percentiles() {
+        let sorted: Vec<f64> = (1..=100).map(f64::from).collect();
+        assert_eq!(percentile(&sorted, 50.0), 50.0);
+        assert_eq!(percentile(&sorted, 99.0), 99.0);
+        assert_eq!(percentile(&[7.0], 95.0), 7.0);
+    }
+
+    #[test]
+    fn it_adds_up_samples() {
+        let samples = vec![
+            sample(Ok(200), 10),
+            sample(Ok(200), 30),
+            sample(Ok(503), 20),
+            sample(Err("timed out"), 30000),
+        ];
+        let stats = Stats::new(&samples, 2, Duration::from_secs(2));
+
+        assert_eq!(stats.requests_per_second, 2.0);
+        assert_eq!(stats.statuses[&200], 2);
+        assert_eq!(stats.statuses[&503], 1);
+        assert_eq!(stats.failures["timed out"], 1);
+        assert_eq!(stats.errors, 2);
+        assert_eq!(stats.error_rate, 0.5);
+        assert_eq!(
+            stats.latency_ms,
+            Some(Latency {
+                min: 10.0,
+                mean: 20.0,
+                p50: 20.0,
+                p90: 30.0,
+                p95: 30.0,
+                p99: 30.0,
+                max: 30.0,
+            })
+        );
+    }
+}
@@ -0,0 +1,246 @@
+use std::cmp::Reverse;
+use std::fs;
+use std::path::Path;
+
+use log::info;
+use number_prefix::{NumberPrefix, Prefixed, Standalone};
+use serde::{Deserialize, Serialize};
+
+use crate::commands::build::wranglerjs::output::WebpackModule;
+use crate::commands::publish::ScriptUploadForm;
+use crate::terminal::message;
+use crate::wasm::WasmModule;
+
+// How many modules and functions are listed in the report
+const TOP_ENTRIES: usize = 10;
+
+// A breakdown of what makes up the built Worker, saved next to the bundle so
+// that the next `build --analyze` can tell what grew.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct Report {
+    pub script: u64,
+    pub modules: Vec<Entry>,
+    pub wasm: Option<WasmReport>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct WasmReport {
+    pub size: u64,
+    pub sections: Vec<Entry>,
+    pub functions: Vec<Entry>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Entry {
+    pub name: String,
+    pub size: u64,
+}
+
+impl Report {
+    pub fn new(
+        form: &ScriptUploadForm,
+        modules: &[WebpackModule],
+    ) -> Result<Report, failure::Error> {
+        let mut report = Report::default();
+
+        for part in &form.parts {
+            if part.mime == "application/wasm" {
+                let module = WasmModule::parse(&part.contents)?;
+                report.wasm = Some(WasmReport {
+                    size: module.size,
+                    sections: module
+                        .sections
+                        .iter()
+                        .map(|s| Entry {
+                            name: s.name.clone(),
+                            size: s.size,
+                        })
+                        .collect(),
+                    functions: module
+                        .largest_functions(TOP_ENTRIES)
+                        .into_iter()
+                        .map(|f| Entry {
+                            name: f.name.clone(),
+                            size: f.size,
+                        })
+                        .collect(),
+                });
+            } else if part.name == "script" {
+                report.script = part.contents.len() as u64;
+            }
+        }
+
+        let mut modules: Vec<Entry> = modules
+            .iter()
+            .map(|m| Entry {
+                name: m.name.clone(),
+                size: m.size,
+            })
+            .collect();
+        modules.sort_by_key(|m| Reverse(m.size));
+        modules.truncate(TOP_ENTRIES);
+        report.modules = modules;
+
+        Ok(report)
+    }
+
+    // Reads the report of a previous build, if there is a usable one.
+    pub fn load(path: &str) -> Option<Report> {
+        let contents = fs::read_to_string(path).ok()?;
+        match serde_json::from_str(&contents) {
+            Ok(report) => Some(report),
+            Err(e) => {
+                info!("ignoring previous report at {}: {}", path, e);
+                None
+            }
+        }
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), failure::Error> {
+        if let Some(dir) = Path::new(path).parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    // Renders the report, with the change since the `previous` report next
+    // to each entry.
+    pub fn render(&self, previous: Option<&Report>) -> String {
+        let mut lines = vec![];
+
+        let previous_script = previous.map(|p| p.script);
+        lines.push(format!("Script: {}", sized(self.script, previous_script)));
+        if !self.modules.is_empty() {
+            lines.push("  Largest modules:".to_string());
+            let previous_modules = previous.map(|p| &p.modules[..]);
+            lines.extend(render_entries(&self.modules, previous_modules));
+        }
+
+        if let Some(wasm) = &self.wasm {
+            let previous_wasm = previous.and_then(|p| p.wasm.as_ref());
+            lines.push(format!(
+                "Wasm: {}",
+                sized(wasm.size, previous_wasm.map(|w| w.size))
+            ));
+            lines.push("  Sections:".to_string());
+            lines.extend(render_entries(
+                &wasm.sections,
+                previous_wasm.map(|w| &w.sections[..]),
+            ));
+            if !wasm.functions.is_empty() {
+                lines.push("  Largest functions:".to_string());
+                lines.extend(render_entries(
+                    &wasm.functions,
+                    previous_wasm.map(|w| &w.functions[..]),
+                ));
+            }
+        }
+
+        lines.join("\n")
+    }
+}
+
+pub fn analyze(
+    form: &ScriptUploadForm,
+    modules: &[WebpackModule],
+    report_path: &str,
+) -> Result<(), failure::Error> {
+    let report = Report::new(form, modules)?;
+    let previous = Report::load(report_path);
+
+    message::info("Bundle analysis");
+    message::detail(&report.render(previous.as_ref()));
+
+    report.save(report_path)?;
+    info!("Saved bundle analysis to {}", report_path);
+    Ok(())
+}
+
+fn render_entries(entries: &[Entry], previous: Option<&[Entry]>) -> Vec<String> {
+    entries
+        .iter()
+        .map(|entry| {
+            // an entry missing from a previous report is new, unless there
+            // was no previous report at all
+            let before = previous.map(|p| {
+                p.iter()
+                    .find(|e| e.name == entry.name)
+                    .map(|e| e.size)
+                    .unwrap_or(0)
+            });
+            format!("    {} {}", entry.name, sized(entry.size, before))
+        })
+        .collect()
+}
+
+fn sized(size: u64, before: Option<u64>) -> String {
+    match before {
+        Some(before) if before != size => format!("{} ({})", human_size(size), delta(size, before)),
+        _ => human_size(size),
+    }
+}
+
+fn delta(size: u64, before: u64) -> String {
+    if size > before {
+        format!("+{}", human_size(size - before))
+    } else {
+        format!("-{}", human_size(before - size))
+    }
+}
+
+fn human_size(bytes: u64) -> String {
+    match NumberPrefix::binary(bytes as f64) {
+        Standalone(bytes) => format!("{} bytes", bytes),
+        Prefixed(prefix, n) => format!("{:.1} {}B", n, prefix),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str, size: u64) -> Entry {
+        Entry {
+            name: name.to_string(),
+            size,
+        }
+    }
+
+    #[test]
+    fn it_renders_the_delta_against_the_previous_report() {
+        let previous = Report {
+            script: 100,
+            modules: vec![entry("./a.js", 60), entry("./b.js", 40)],
+            wasm: None,
+        };
+        let report = Report {
+            script: 130,
+            modules: vec![entry("./a.js", 60), entry("./c.js", 70)],
+            wasm: None,
+        };
+
+        let rendered = report.render(Some(&previous));
+        assert!(rendered.contains("Script: 130 bytes (+30 bytes)"));
+        assert!(rendered.contains("./a.js 60 bytes\n"));
+        assert!(rendered.contains("./c.js 70 bytes (+70 bytes)"));
+    }
+
+    #[test]
+    fn it_renders_without_a_previous_report() {
+        let report = Report {
+            script: 130,
+            modules: vec![],
+            wasm: Some(WasmReport {
+                size: 2048,
+                sections: vec![entry("code", 1024)],
+                functions: vec![],
+            }),
+        };
+
+        let rendered = report.render(None);
+        assert!(rendered.contains("Script: 130 bytes\n"));
+        assert!(rendered.contains("Wasm: 2.0 KiB"));
+        assert!(rendered.contains("code 1.0 KiB"));
+    }
+}
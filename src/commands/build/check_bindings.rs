@@ -0,0 +1,105 @@
+use std::collections::BTreeSet;
+
+use crate::commands::publish::ScriptUploadForm;
+use crate::js::Identifiers;
+use crate::terminal::message;
+
+// What the bound names of a Worker look like from its script: names it uses
+// that nothing declares, and declared bindings it never uses.
+#[derive(Debug, PartialEq)]
+pub struct BindingsReport {
+    pub undeclared: Vec<String>,
+    pub unused: Vec<String>,
+}
+
+impl BindingsReport {
+    pub fn new(script: &str, bindings: &[String]) -> BindingsReport {
+        let identifiers = Identifiers::scan(script);
+        let bindings: BTreeSet<&String> = bindings.iter().collect();
+
+        BindingsReport {
+            undeclared: identifiers
+                .free()
+                .into_iter()
+                .filter(|name| !bindings.contains(name))
+                .collect(),
+            unused: bindings
+                .into_iter()
+                .filter(|name| !identifiers.referenced.contains(*name))
+                .cloned()
+                .collect(),
+        }
+    }
+}
+
+// Statically checks the names the script expects to be bound against the
+// bindings in the metadata, so that a missing binding is reported before it
+// turns into a `ReferenceError` at runtime. This is a heuristic, so it only
+// ever warns. Unused Wasm bindings are left to `validate_wasm`, which warns
+// about them before each upload.
+pub fn check_bindings(form: &ScriptUploadForm) -> Result<(), failure::Error> {
+    let bindings = form.bindings()?;
+    let names: Vec<String> = bindings.iter().map(|b| b.name.clone()).collect();
+    let report = BindingsReport::new(&form.script(), &names);
+
+    if !report.undeclared.is_empty() {
+        message::warn(&format!(
+            "Your script uses globals that are neither declared in it nor bound to it: {}. Are you missing a binding?",
+            report.undeclared.join(", ")
+        ));
+    }
+    let wasm_bindings: Vec<&String> = bindings
+        .iter()
+        .filter(|b| b.binding_type == "wasm_module")
+        .map(|b| &b.name)
+        .collect();
+    for name in report.unused.iter().filter(|n| !wasm_bindings.contains(n)) {
+        message::warn(&format!(
+            "The binding `{}` is declared but your script never uses it.",
+            name
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_reports_undeclared_and_unused_bindings() {
+        let script = r#"
+            const window = this;
+            addEventListener('fetch', event => {
+                event.respondWith(MY_KV.get(event.request.url).then(v => new Response(v)))
+            })
+        "#;
+        let bindings = vec!["wasmprogram".to_string()];
+
+        assert_eq!(
+            BindingsReport::new(script, &bindings),
+            BindingsReport {
+                undeclared: vec!["MY_KV".to_string()],
+                unused: vec!["wasmprogram".to_string()],
+            }
+        );
+    }
+
+    #[test]
+    fn it_accepts_bound_names() {
+        // the wasm-bindgen glue followed by worker.js, as a Rust build writes
+        let script = r#"
+            (function() {
+                function init(module) {}
+                self.wasm_bindgen = Object.assign(init, {});
+            })();
+            wasm_bindgen(wasmprogram)
+        "#;
+        let bindings = vec!["wasmprogram".to_string()];
+        let report = BindingsReport::new(script, &bindings);
+
+        assert!(report.undeclared.is_empty());
+        assert!(report.unused.is_empty());
+    }
+}
@@ -0,0 +1,311 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use log::info;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use super::wasm_opt;
+use super::watch::IGNORED_DIRS;
+use super::wranglerjs::Bundle;
+use crate::commands::publish::krate::Krate;
+use crate::install;
+use crate::settings::project::{Project, ProjectType};
+
+// Where the inputs and outputs of the last successful build are recorded,
+// next to its output.
+const MANIFEST_PATH: &str = "worker/build_manifest.json";
+
+// Files in the project that the build does not read: the settings it does
+// depend on are recorded on their own, and the lock is only held during npm
+// installs
+const NOT_INPUTS: &[&str] = &["wrangler.toml", ".install.lock"];
+
+// A fingerprint of what a build depends on and of what it produced. If the
+// inputs match the ones recorded by the previous build and its outputs are
+// still there as it left them, the build can be skipped.
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+pub struct BuildManifest {
+    // the settings and tools the build depends on
+    settings: BTreeMap<String, String>,
+    // sha256 of each input file, keyed by path
+    inputs: BTreeMap<String, String>,
+    // sha256 of each output file, keyed by path
+    outputs: BTreeMap<String, String>,
+    #[serde(skip)]
+    root: PathBuf,
+}
+
+impl BuildManifest {
+    // The manifest of the inputs of the project at `root`, as they are now.
+    pub fn new(project: &Project, root: &Path) -> Result<BuildManifest, failure::Error> {
+        let mut settings = BTreeMap::new();
+        settings.insert("type".to_string(), project.project_type.to_string());
+        if let ProjectType::Webpack = project.project_type {
+            settings.insert(
+                "webpack_config".to_string(),
+                project.webpack_config.clone().unwrap_or_default(),
+            );
+            // wranglerjs is released with wrangler
+            let version = if install::target::DEBUG {
+                "dev"
+            } else {
+                env!("CARGO_PKG_VERSION")
+            };
+            settings.insert("wranglerjs".to_string(), version.to_string());
+            settings.insert(
+                "webpack".to_string(),
+                serde_json::to_string(&project.webpack_build())?,
+            );
+        }
+        if let ProjectType::Rust = project.project_type {
+            let rust = project.rust_build();
+            settings.insert("rust".to_string(), serde_json::to_string(&rust)?);
+        }
+        if let Some(wasm_opt) = project.wasm_opt() {
+            settings.insert("wasm_opt".to_string(), serde_json::to_string(&wasm_opt)?);
+            settings.insert(
+                "binaryen".to_string(),
+                wasm_opt::BINARYEN_VERSION.to_string(),
+            );
+        }
+
+        Ok(BuildManifest {
+            settings,
+            inputs: hash_files(root, &inputs(project, root)?)?,
+            outputs: BTreeMap::new(),
+            root: root.to_path_buf(),
+        })
+    }
+
+    // Whether the previous build had the same inputs, and its outputs have
+    // not changed since. A missing or unreadable manifest is treated as a
+    // change.
+    pub fn is_fresh(&self, project: &Project) -> bool {
+        let path = self.root.join(MANIFEST_PATH);
+        let previous = match fs::read_to_string(&path) {
+            Ok(contents) => match serde_json::from_str::<BuildManifest>(&contents) {
+                Ok(previous) => previous,
+                Err(e) => {
+                    info!("could not parse {}: {}", path.display(), e);
+                    return false;
+                }
+            },
+            Err(_) => return false,
+        };
+        if previous.settings != self.settings || previous.inputs != self.inputs {
+            return false;
+        }
+
+        match hash_files(&self.root, &outputs(project, &self.root)) {
+            Ok(outputs) => !outputs.is_empty() && outputs == previous.outputs,
+            Err(e) => {
+                info!("could not hash the build output: {}", e);
+                false
+            }
+        }
+    }
+
+    // Records the outputs of the build that just ran on these inputs.
+    pub fn save(mut self, project: &Project) -> Result<(), failure::Error> {
+        self.outputs = hash_files(&self.root, &outputs(project, &self.root))?;
+
+        let path = self.root.join(MANIFEST_PATH);
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        info!("Writing build manifest to {}", path.display());
+        fs::write(&path, serde_json::to_string_pretty(&self)?)?;
+        Ok(())
+    }
+}
+
+// The files the build reads: the sources, the lockfile and, for webpack, its
+// config, which all live in the project.
+fn inputs(project: &Project, root: &Path) -> Result<Vec<PathBuf>, failure::Error> {
+    let mut files = vec![];
+    match project.project_type {
+        ProjectType::JavaScript => {}
+        ProjectType::Rust => {
+            // the crate may depend on other members of its workspace, so
+            // everything in the workspace but the build output is an input
+            let krate = Krate::new(root, &project.rust_build())?;
+            let out_dir = root.join(project.rust_build().out_dir());
+            walk(&krate.workspace_root, &mut files)?;
+            files.retain(|f| !f.starts_with(&out_dir) && !f.starts_with(&krate.target_dir));
+            files.push(root.join("worker").join("worker.js"));
+        }
+        ProjectType::Webpack => {
+            walk(root, &mut files)?;
+            if let Some(config) = &project.webpack_config {
+                files.push(root.join(config));
+            }
+        }
+    }
+    files.retain(|f| match f.strip_prefix(root) {
+        Ok(name) => !NOT_INPUTS.iter().any(|n| name == Path::new(n)),
+        Err(_) => true,
+    });
+
+    files.sort();
+    files.dedup();
+    Ok(files)
+}
+
+// The files the build writes
+fn outputs(project: &Project, root: &Path) -> Vec<PathBuf> {
+    let mut files = vec![];
+    match project.project_type {
+        ProjectType::JavaScript => {}
+        ProjectType::Rust => {
+            let out_dir = root.join(project.rust_build().out_dir());
+            if walk(&out_dir, &mut files).is_err() {
+                return vec![];
+            }
+            files.extend(bundle_files(root));
+        }
+        ProjectType::Webpack => files.extend(bundle_files(root)),
+    }
+    files
+}
+
+// The script, Wasm and metadata of the bundle, which both Rust and webpack
+// builds write
+fn bundle_files(root: &Path) -> Vec<PathBuf> {
+    let bundle = Bundle::new();
+    vec![
+        root.join(bundle.script_path()),
+        root.join(bundle.wasm_path()),
+        root.join(bundle.metadata_path()),
+    ]
+}
+
+// Adds the files under `dir` to `files`, leaving out build output and
+// dependencies.
+fn walk(dir: &Path, files: &mut Vec<PathBuf>) -> Result<(), failure::Error> {
+    if !dir.is_dir() {
+        return Ok(());
+    }
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            let name = path
+                .file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or_default();
+            if !IGNORED_DIRS.contains(&name) {
+                walk(&path, files)?;
+            }
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+// sha256 of each of the `files` that exist, keyed by their path from `root`
+fn hash_files(root: &Path, files: &[PathBuf]) -> Result<BTreeMap<String, String>, failure::Error> {
+    let mut hashes = BTreeMap::new();
+    for file in files {
+        if !file.is_file() {
+            continue;
+        }
+        let name = file.strip_prefix(root).unwrap_or(file);
+        let contents = fs::read(file)?;
+        hashes.insert(
+            name.to_string_lossy().replace('\\', "/"),
+            format!("{:x}", Sha256::digest(&contents)),
+        );
+    }
+    Ok(hashes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::settings::project::{Build, RustBuild};
+
+    #[test]
+    fn it_is_fresh_until_inputs_or_outputs_change() {
+        let root = std::env::temp_dir().join(format!("wrangler-manifest-{}", std::process::id()));
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::create_dir_all(root.join("pkg")).unwrap();
+        fs::write(
+            root.join("Cargo.toml"),
+            "[package]\nname = \"a\"\nversion = \"0.1.0\"\n",
+        )
+        .unwrap();
+        fs::write(root.join("src").join("lib.rs"), "fn a() {}").unwrap();
+        fs::write(root.join("pkg").join("a_bg.wasm"), "wasm").unwrap();
+
+        let mut project = Project {
+            name: "test".to_string(),
+            project_type: ProjectType::Rust,
+            zone_id: None,
+            private: None,
+            webpack_config: None,
+            account_id: "account".to_string(),
+            route: None,
+            routes: None,
+            kv_namespaces: None,
+            deploy_state: None,
+            plan: None,
+            size_limits: None,
+            build: None,
+        };
+
+        let manifest = BuildManifest::new(&project, &root).unwrap();
+        assert!(!manifest.is_fresh(&project));
+        manifest.save(&project).unwrap();
+        assert!(BuildManifest::new(&project, &root)
+            .unwrap()
+            .is_fresh(&project));
+
+        // a build that was tampered with is not fresh
+        fs::write(root.join("pkg").join("a_bg.wasm"), "other").unwrap();
+        assert!(!BuildManifest::new(&project, &root)
+            .unwrap()
+            .is_fresh(&project));
+        BuildManifest::new(&project, &root)
+            .unwrap()
+            .save(&project)
+            .unwrap();
+
+        // and neither is one of sources that have changed since
+        fs::write(root.join("src").join("lib.rs"), "fn b() {}").unwrap();
+        assert!(!BuildManifest::new(&project, &root)
+            .unwrap()
+            .is_fresh(&project));
+        BuildManifest::new(&project, &root)
+            .unwrap()
+            .save(&project)
+            .unwrap();
+
+        // including the sources of path dependencies in the workspace
+        fs::create_dir_all(root.join("shared").join("src")).unwrap();
+        fs::write(root.join("shared").join("src").join("lib.rs"), "").unwrap();
+        assert!(!BuildManifest::new(&project, &root)
+            .unwrap()
+            .is_fresh(&project));
+        BuildManifest::new(&project, &root)
+            .unwrap()
+            .save(&project)
+            .unwrap();
+
+        // nor is one configured with another backend
+        project.build = Some(Build {
+            rust: Some(RustBuild {
+                backend: Some("cargo".to_string()),
+                ..RustBuild::default()
+            }),
+            ..Build::default()
+        });
+        assert!(!BuildManifest::new(&project, &root)
+            .unwrap()
+            .is_fresh(&project));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
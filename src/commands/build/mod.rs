@@ -1,37 +1,236 @@
+mod analyze;
+mod check_bindings;
+mod manifest;
+pub mod size;
+mod wasm_bindgen;
+mod wasm_opt;
+pub mod watch;
 pub mod wranglerjs;
 
-use crate::settings::project::{Project, ProjectType};
+use crate::commands::publish;
+use crate::commands::publish::krate::Krate;
+use crate::settings::project::{Project, ProjectType, RustBuild};
 use crate::{commands, install};
-use std::path::PathBuf;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
 use crate::terminal::message;
+use manifest::BuildManifest;
 
-pub fn build(project: &Project) -> Result<(), failure::Error> {
+// Options of a single build, as passed on the command line.
+#[derive(Debug, Default)]
+pub struct BuildOptions {
+    pub analyze: bool,
+    // Build even if nothing changed since the last build
+    pub force: bool,
+}
+
+pub fn build(project: &Project, options: &BuildOptions) -> Result<(), failure::Error> {
+    let mut webpack_modules = vec![];
     let project_type = &project.project_type;
+    let manifest = match project_type {
+        ProjectType::JavaScript => None,
+        _ => Some(BuildManifest::new(project, &env::current_dir()?)?),
+    };
+    // an analysis needs the modules reported by webpack, so it always builds
+    let fresh = !options.force
+        && !options.analyze
+        && manifest.as_ref().map(|m| m.is_fresh(project)) == Some(true);
+
     match project_type {
         ProjectType::JavaScript => {
             message::info("JavaScript project found. Skipping unnecessary build!")
         }
+        _ if fresh => message::info(
+            "Your project is unchanged since the last build, skipping it. Pass `--force-build` to build it anyway.",
+        ),
         ProjectType::Rust => {
-            let tool_name = "wasm-pack";
-            let binary_path = install::install(tool_name, "rustwasm")?.binary(tool_name)?;
-            let args = ["build", "--target", "no-modules"];
+            let rust = project.rust_build();
+            let root = fs::canonicalize(env::current_dir()?)?;
+            let krate = Krate::new(&root, &rust)?;
+
+            match wasm_pack(&rust)? {
+                Some(binary_path) => {
+                    let args = wasm_pack_args(&rust, &krate.dir, &root)?;
+
+                    let command = command(&args, binary_path);
+                    let command_name = format!("{:?}", command);
 
-            let command = command(&args, binary_path);
-            let command_name = format!("{:?}", command);
+                    commands::run(command, &command_name)?;
+                }
+                None => {
+                    if !rust.wasm_pack_args.clone().unwrap_or_default().is_empty() {
+                        message::warn(
+                            "`wasm_pack_args` only apply to wasm-pack, and are ignored when building with cargo",
+                        );
+                    }
+                    wasm_bindgen::build(&krate, &rust, &root)?
+                }
+            }
 
-            commands::run(command, &command_name)?;
+            write_rust_bundle(project, &rust, &krate)?;
         }
         ProjectType::Webpack => {
-            wranglerjs::run_build(project)?;
+            let output = wranglerjs::run_build(project, options.analyze)?;
+            webpack_modules = output.modules;
         }
     }
+    if let (Some(manifest), false) = (manifest, fresh) {
+        manifest.save(project)?;
+    }
+
+    let script_upload_form = report(project)?;
+
+    if options.analyze {
+        let report_path = wranglerjs::Bundle::new().analyze_path();
+        analyze::analyze(&script_upload_form, &webpack_modules, &report_path)?;
+    }
 
     Ok(())
 }
 
-fn command(args: &[&str], binary_path: PathBuf) -> Command {
+// Builds the project again whenever its sources change, until interrupted. A
+// failed build is reported, and the next change is waited for. Webpack
+// watches the sources itself, in a single long-running wranglerjs.
+pub fn watch(project: &Project, options: &BuildOptions) -> Result<(), failure::Error> {
+    if let ProjectType::Webpack = project.project_type {
+        message::working("Building your project, and again whenever its sources change");
+        return wranglerjs::run_watch(project, |output| {
+            let result = output.and_then(|_| {
+                BuildManifest::new(project, &env::current_dir()?)?.save(project)?;
+                report(project)
+            });
+            if let Err(e) = result {
+                message::user_error(&format!("Build failed: {}", e));
+            }
+            message::info("Watching for changes...");
+        });
+    }
+
+    let watcher = watch::Watcher::new(project)?;
+    let mut result = build(project, options);
+    loop {
+        if let Err(e) = result {
+            message::user_error(&format!("Build failed: {}", e));
+        }
+        message::info("Watching for changes...");
+        let changed = watcher.wait()?;
+        message::working(&format!(
+            "{} changed, rebuilding",
+            watch::describe(&changed)
+        ));
+        result = build(project, options);
+    }
+}
+
+// Writes what wasm-pack built as the bundle of the project: the wasm-bindgen
+// glue followed by worker.js as the script, and the Wasm bound to it as `wasm`.
+fn write_rust_bundle(
+    project: &Project,
+    rust: &RustBuild,
+    krate: &Krate,
+) -> Result<(), failure::Error> {
+    let name = &krate.out_name;
+    let out_dir = PathBuf::from(rust.out_dir());
+
+    let bindgen_js = fs::read_to_string(out_dir.join(format!("{}.js", name)))?;
+    let worker_js = fs::read_to_string(Path::new("worker").join("worker.js"))?;
+    let wasm = fs::read(out_dir.join(format!("{}_bg.wasm", name)))?;
+
+    let script = format!("{} {}", bindgen_js, worker_js);
+    wranglerjs::Bundle::for_project(project).write_parts(&script, Some(wasm))
+}
+
+// Reports the size of what was built, and checks its bindings.
+fn report(project: &Project) -> Result<publish::ScriptUploadForm, failure::Error> {
+    let script_upload_form = publish::build_script_upload_form(project)?;
+    let size = size::ProjectSize::new(&script_upload_form);
+    let msg = format!(
+        "Built successfully, built project size is {}",
+        size.message(project.size_limit())
+    );
+    message::success(&msg);
+
+    check_bindings::check_bindings(&script_upload_form)?;
+
+    Ok(script_upload_form)
+}
+
+// The wasm-pack to build a Rust project with, or none to build it with cargo
+// and wasm-bindgen: when the `backend` is `cargo`, or when no backend is set
+// and wasm-pack cannot be installed.
+fn wasm_pack(rust: &RustBuild) -> Result<Option<PathBuf>, failure::Error> {
+    let tool_name = "wasm-pack";
+    let install = || install::install(tool_name, "rustwasm").and_then(|d| d.binary(tool_name));
+    match &rust.backend {
+        None => match install() {
+            Ok(binary_path) => Ok(Some(binary_path)),
+            Err(e) => {
+                message::warn(&format!(
+                    "Could not install wasm-pack ({}); building with cargo and wasm-bindgen instead",
+                    e
+                ));
+                Ok(None)
+            }
+        },
+        Some(backend) => match backend.as_str() {
+            "wasm-pack" => Ok(Some(install()?)),
+            "cargo" => Ok(None),
+            _ => failure::bail!(
+                "Unknown Rust build backend `{}`; use wasm-pack or cargo",
+                backend
+            ),
+        },
+    }
+}
+
+// The arguments of `wasm-pack build` for the `[build.rust]` settings. Cargo
+// features are passed on to cargo, after any arguments for it among the extra
+// arguments. A crate elsewhere than at the `root` of the project is built
+// where it is, and wasm-pack writes to the out dir of the project, which it
+// would otherwise take as relative to the crate.
+fn wasm_pack_args(
+    rust: &RustBuild,
+    krate_dir: &Path,
+    root: &Path,
+) -> Result<Vec<String>, failure::Error> {
+    let mut args: Vec<String> = vec!["build".into()];
+    let out_dir = if krate_dir == root {
+        rust.out_dir()
+    } else {
+        args.push(krate_dir.to_string_lossy().to_string());
+        root.join(rust.out_dir()).to_string_lossy().to_string()
+    };
+    args.push("--target".into());
+    args.push("no-modules".into());
+    if let Some(profile) = &rust.profile {
+        match profile.as_str() {
+            "dev" | "release" | "profiling" => args.push(format!("--{}", profile)),
+            _ => failure::bail!(
+                "Unknown Rust build profile `{}`; use dev, release or profiling",
+                profile
+            ),
+        }
+    }
+    args.push("--out-dir".into());
+    args.push(out_dir);
+    args.extend(rust.wasm_pack_args.clone().unwrap_or_default());
+
+    let features = rust.features.clone().unwrap_or_default();
+    if !features.is_empty() {
+        if !args.iter().any(|a| a == "--") {
+            args.push("--".into());
+        }
+        args.push("--features".into());
+        args.push(features.join(","));
+    }
+
+    Ok(args)
+}
+
+fn command(args: &[String], binary_path: PathBuf) -> Command {
     message::working("Compiling your project to WebAssembly...");
 
     let mut c = if cfg!(target_os = "windows") {
@@ -46,3 +245,59 @@ fn command(args: &[&str], binary_path: PathBuf) -> Command {
     c.args(args);
     c
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_passes_rust_build_settings_to_wasm_pack() {
+        let root = Path::new("/ws");
+        assert_eq!(
+            wasm_pack_args(&RustBuild::default(), root, root).unwrap(),
+            vec!["build", "--target", "no-modules", "--out-dir", "pkg"]
+        );
+
+        let rust = RustBuild {
+            profile: Some("dev".to_string()),
+            features: Some(vec!["a".to_string(), "b".to_string()]),
+            wasm_pack_args: Some(vec!["--no-typescript".to_string()]),
+            out_dir: Some("out".to_string()),
+            ..RustBuild::default()
+        };
+        assert_eq!(
+            wasm_pack_args(&rust, root, root).unwrap(),
+            vec![
+                "build",
+                "--target",
+                "no-modules",
+                "--dev",
+                "--out-dir",
+                "out",
+                "--no-typescript",
+                "--",
+                "--features",
+                "a,b"
+            ]
+        );
+
+        let rust = RustBuild {
+            profile: Some("fast".to_string()),
+            ..RustBuild::default()
+        };
+        assert!(wasm_pack_args(&rust, root, root).is_err());
+
+        // a member of a workspace is built in its directory, into the project
+        assert_eq!(
+            wasm_pack_args(&RustBuild::default(), Path::new("/ws/workers/api"), root).unwrap(),
+            vec![
+                "build",
+                "/ws/workers/api",
+                "--target",
+                "no-modules",
+                "--out-dir",
+                "/ws/pkg"
+            ]
+        );
+    }
+}
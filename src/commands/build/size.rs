@@ -0,0 +1,161 @@
+use crate::commands::publish::ScriptUploadForm;
+use crate::terminal::emoji;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use number_prefix::{NumberPrefix, Prefixed, Standalone};
+use std::io::prelude::*;
+
+// Warn when less than 80 KiB are left to grow
+const WARN_MARGIN: u64 = 81_920;
+
+// The gzipped size of what is uploaded for a Worker, which is what counts
+// against the size limit of the plan.
+#[derive(Debug, PartialEq)]
+pub struct ProjectSize {
+    pub script: u64,
+    pub wasm: u64,
+}
+
+impl ProjectSize {
+    pub fn new(form: &ScriptUploadForm) -> ProjectSize {
+        let mut size = ProjectSize { script: 0, wasm: 0 };
+
+        for part in &form.parts {
+            if part.mime == "application/wasm" {
+                size.wasm += gzip_size(&part.contents);
+            } else if part.name == "script" {
+                size.script += gzip_size(&part.contents);
+            }
+        }
+
+        size
+    }
+
+    pub fn total(&self) -> u64 {
+        self.script + self.wasm
+    }
+
+    pub fn exceeds(&self, limit: u64) -> bool {
+        self.total() > limit
+    }
+
+    pub fn message(&self, limit: u64) -> String {
+        let breakdown = if self.wasm > 0 {
+            format!(
+                "{} (script {}, Wasm {})",
+                human_size(self.total()),
+                human_size(self.script),
+                human_size(self.wasm)
+            )
+        } else {
+            human_size(self.total())
+        };
+
+        match limit.checked_sub(self.total()) {
+            Some(bytes_left) if bytes_left <= WARN_MARGIN => format!(
+                "{size}. {warn} Your built project is {left} away from reaching the {limit} size limit. {warn}",
+                size = breakdown,
+                left = human_size(bytes_left),
+                limit = human_size(limit),
+                warn = emoji::WARN,
+            ),
+            Some(_) => format!("{}.", breakdown),
+            None => format!(
+                "{size}. {warn} Your built project has grown past the {limit} size limit and may fail to deploy. {warn}",
+                size = breakdown,
+                limit = human_size(limit),
+                warn = emoji::WARN,
+            ),
+        }
+    }
+}
+
+fn gzip_size(contents: &[u8]) -> u64 {
+    let mut e = GzEncoder::new(Vec::new(), Compression::default());
+    e.write_all(contents)
+        .expect("could not write upload buffer");
+    e.finish().expect("failed to compress project").len() as u64
+}
+
+pub fn human_size(bytes: u64) -> String {
+    match NumberPrefix::binary(bytes as f64) {
+        Standalone(bytes) => format!("{} bytes", bytes),
+        Prefixed(prefix, n) => format!("{:.0} {}B", n, prefix),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::publish::script_upload_form::UploadPart;
+
+    const LIMIT: u64 = 1 << 20;
+
+    fn part(name: &str, mime: &str, contents: &[u8]) -> UploadPart {
+        UploadPart {
+            name: name.to_string(),
+            file_name: name.to_string(),
+            mime: mime.to_string(),
+            contents: contents.to_vec(),
+        }
+    }
+
+    #[test]
+    fn it_warns_over_max_size() {
+        let size = ProjectSize {
+            script: 1 << 21,
+            wasm: 0,
+        };
+        assert!(size.exceeds(LIMIT));
+        assert!(size
+            .message(LIMIT)
+            .contains("grown past the 1 MiB size limit"));
+    }
+
+    #[test]
+    fn it_warns_near_max_size() {
+        let size = ProjectSize {
+            script: LIMIT - 4096,
+            wasm: 0,
+        };
+        assert!(!size.exceeds(LIMIT));
+        assert!(size.message(LIMIT).contains("reaching"));
+    }
+
+    #[test]
+    fn it_uses_the_configured_limit() {
+        let size = ProjectSize {
+            script: 1 << 21,
+            wasm: 0,
+        };
+        assert!(!size.exceeds(1 << 22));
+        assert!(!size.message(1 << 22).contains(emoji::WARN.0));
+    }
+
+    #[test]
+    fn it_returns_project_size_with_wasm() {
+        let form = ScriptUploadForm {
+            parts: vec![
+                part("metadata", "application/json", b"{}"),
+                part("script", "application/javascript", b"abcdefg"),
+                part("wasmprogram", "application/wasm", &[0, 97, 115, 109]),
+            ],
+        };
+        let size = ProjectSize::new(&form);
+
+        assert_eq!(size.script, 27);
+        assert_eq!(size.wasm, 24);
+        assert!(size.message(LIMIT).contains("Wasm"));
+    }
+
+    #[test]
+    fn it_returns_project_size_without_wasm() {
+        let form = ScriptUploadForm {
+            parts: vec![part("script", "application/javascript", b"abcdefg")],
+        };
+        let size = ProjectSize::new(&form);
+
+        assert_eq!(size.total(), 27);
+        assert!(!size.message(LIMIT).contains("Wasm"));
+    }
+}
@@ -0,0 +1,218 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use serde::Deserialize;
+
+use crate::commands::publish::krate::Krate;
+use crate::settings::project::RustBuild;
+use crate::terminal::message;
+use crate::{commands, install};
+
+const TARGET: &str = "wasm32-unknown-unknown";
+
+#[derive(Debug, Deserialize)]
+struct Lockfile {
+    #[serde(default)]
+    package: Vec<LockedPackage>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LockedPackage {
+    name: String,
+    version: String,
+}
+
+// Builds the crate for wasm32 with cargo, then generates its bindings into
+// the out dir of the project at `root`, as wasm-pack would. The wasm-bindgen
+// CLI has to be the version of the library the crate is locked to.
+pub fn build(krate: &Krate, rust: &RustBuild, root: &Path) -> Result<(), failure::Error> {
+    message::working("Compiling your project to WebAssembly with cargo...");
+    let mut command = Command::new("cargo");
+    // the project may not be in the crate's workspace
+    command
+        .args(cargo_args(krate, rust)?)
+        .current_dir(&krate.dir);
+    let command_name = format!("{:?}", command);
+    commands::run(command, &command_name)?;
+
+    // the build has written the lockfile if there was none
+    let lockfile = fs::read_to_string(krate.workspace_root.join("Cargo.lock"))?;
+    let version = locked_version(&lockfile)?;
+    let tool_name = "wasm-bindgen";
+    let binary_path =
+        install::install_version(tool_name, "rustwasm", &version)?.binary(tool_name)?;
+
+    let wasm = wasm_path(krate, rust)?;
+    let mut command = Command::new(binary_path);
+    command.args(wasm_bindgen_args(&wasm, rust, &root.join(rust.out_dir())));
+    let command_name = format!("{:?}", command);
+    commands::run(command, &command_name)
+}
+
+// Whether the `[build.rust]` profile is a debug build. wasm-pack builds for
+// release by default, and so does this.
+fn is_dev(rust: &RustBuild) -> Result<bool, failure::Error> {
+    match &rust.profile {
+        None => Ok(false),
+        Some(profile) => match profile.as_str() {
+            "dev" => Ok(true),
+            "release" | "profiling" => Ok(false),
+            _ => failure::bail!(
+                "Unknown Rust build profile `{}`; use dev, release or profiling",
+                profile
+            ),
+        },
+    }
+}
+
+fn cargo_args(krate: &Krate, rust: &RustBuild) -> Result<Vec<String>, failure::Error> {
+    let mut args: Vec<String> = vec![
+        "build".into(),
+        "--lib".into(),
+        "--target".into(),
+        TARGET.into(),
+        "--package".into(),
+        krate.name.clone(),
+    ];
+    if !is_dev(rust)? {
+        args.push("--release".into());
+    }
+
+    let features = rust.features.clone().unwrap_or_default();
+    if !features.is_empty() {
+        args.push("--features".into());
+        args.push(features.join(","));
+    }
+
+    Ok(args)
+}
+
+// The Wasm cargo built
+fn wasm_path(krate: &Krate, rust: &RustBuild) -> Result<PathBuf, failure::Error> {
+    let profile_dir = if is_dev(rust)? { "debug" } else { "release" };
+    Ok(krate
+        .target_dir
+        .join(TARGET)
+        .join(profile_dir)
+        .join(format!("{}.wasm", krate.out_name)))
+}
+
+fn wasm_bindgen_args(wasm: &Path, rust: &RustBuild, out_dir: &Path) -> Vec<String> {
+    let mut args: Vec<String> = vec![
+        wasm.to_string_lossy().to_string(),
+        "--out-dir".into(),
+        out_dir.to_string_lossy().to_string(),
+        "--target".into(),
+        "no-modules".into(),
+        "--no-typescript".into(),
+    ];
+    // keep the debug info of the profiles that have it, like wasm-pack
+    if let Some(profile) = &rust.profile {
+        match profile.as_str() {
+            "dev" => {
+                args.push("--debug".into());
+                args.push("--keep-debug".into());
+            }
+            "profiling" => args.push("--keep-debug".into()),
+            _ => {}
+        }
+    }
+
+    args
+}
+
+// The version of wasm-bindgen in the Cargo.lock `lockfile`
+fn locked_version(lockfile: &str) -> Result<String, failure::Error> {
+    let lockfile: Lockfile = toml::from_str(lockfile)?;
+    match lockfile
+        .package
+        .into_iter()
+        .find(|p| p.name == "wasm-bindgen")
+    {
+        Some(package) => Ok(package.version),
+        None => failure::bail!(
+            "Your crate does not depend on wasm-bindgen, which is needed to build it with cargo"
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn krate() -> Krate {
+        Krate {
+            name: "api-worker".to_string(),
+            out_name: "api_worker".to_string(),
+            dir: PathBuf::from("/ws/workers/api"),
+            workspace_root: PathBuf::from("/ws"),
+            target_dir: PathBuf::from("/ws/target"),
+        }
+    }
+
+    #[test]
+    fn it_builds_with_cargo_then_wasm_bindgen() {
+        let rust = RustBuild::default();
+        assert_eq!(
+            cargo_args(&krate(), &rust).unwrap(),
+            vec![
+                "build",
+                "--lib",
+                "--target",
+                "wasm32-unknown-unknown",
+                "--package",
+                "api-worker",
+                "--release"
+            ]
+        );
+        assert_eq!(
+            wasm_path(&krate(), &rust).unwrap(),
+            PathBuf::from("/ws/target/wasm32-unknown-unknown/release/api_worker.wasm")
+        );
+
+        let rust = RustBuild {
+            profile: Some("dev".to_string()),
+            features: Some(vec!["a".to_string()]),
+            ..RustBuild::default()
+        };
+        assert_eq!(
+            cargo_args(&krate(), &rust).unwrap()[6..].to_vec(),
+            vec!["--features", "a"]
+        );
+        let wasm = wasm_path(&krate(), &rust).unwrap();
+        assert_eq!(
+            wasm,
+            PathBuf::from("/ws/target/wasm32-unknown-unknown/debug/api_worker.wasm")
+        );
+        assert_eq!(
+            wasm_bindgen_args(&wasm, &rust, Path::new("/ws/pkg")),
+            vec![
+                "/ws/target/wasm32-unknown-unknown/debug/api_worker.wasm",
+                "--out-dir",
+                "/ws/pkg",
+                "--target",
+                "no-modules",
+                "--no-typescript",
+                "--debug",
+                "--keep-debug"
+            ]
+        );
+    }
+
+    #[test]
+    fn it_reads_the_locked_wasm_bindgen_version() {
+        let lockfile = r#"
+[[package]]
+name = "api-worker"
+version = "0.1.0"
+
+[[package]]
+name = "wasm-bindgen"
+version = "0.2.50"
+source = "registry+https://github.com/rust-lang/crates.io-index"
+"#;
+        assert_eq!(locked_version(lockfile).unwrap(), "0.2.50");
+        assert!(locked_version("").is_err());
+    }
+}
@@ -0,0 +1,94 @@
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use super::size::human_size;
+use crate::settings::project::WasmOpt;
+use crate::terminal::message;
+use crate::{commands, install};
+
+// The release of binaryen wasm-opt is installed from
+pub const BINARYEN_VERSION: &str = "90";
+
+// Optimises the Wasm at `path` in place with wasm-opt, and reports how much
+// smaller it got.
+pub fn optimize(path: &Path, options: &WasmOpt) -> Result<(), failure::Error> {
+    let tool_name = "wasm-opt";
+    let binary_path = install::install_binaryen(tool_name, BINARYEN_VERSION)?.binary(tool_name)?;
+    let before = fs::metadata(path)?.len();
+
+    message::working("Optimizing your WebAssembly with wasm-opt...");
+    let mut command = Command::new(binary_path);
+    command.args(args(path, options)?);
+    let command_name = format!("{:?}", command);
+    commands::run(command, &command_name)?;
+
+    let after = fs::metadata(path)?.len();
+    message::info(&format!(
+        "wasm-opt -O{} took {} from {} to {}",
+        options.level(),
+        path.display(),
+        human_size(before),
+        human_size(after)
+    ));
+    Ok(())
+}
+
+// The arguments of wasm-opt to optimise the Wasm at `path` in place
+fn args(path: &Path, options: &WasmOpt) -> Result<Vec<String>, failure::Error> {
+    let level = options.level();
+    match level.as_str() {
+        "0" | "1" | "2" | "3" | "4" | "s" | "z" => {}
+        _ => failure::bail!("Unknown wasm-opt level `{}`; use 0 to 4, s or z", level),
+    }
+
+    let path = path.to_string_lossy().to_string();
+    let mut args = vec![format!("-O{}", level)];
+    if options.strip() {
+        // the name section is debug info to binaryen, and the producers
+        // section is the only other custom section the toolchain emits
+        args.push("--strip-debug".into());
+        args.push("--strip-producers".into());
+    }
+    args.push(path.clone());
+    args.push("-o".into());
+    args.push(path);
+
+    Ok(args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_passes_wasm_opt_settings_to_wasm_opt() {
+        let path = Path::new("pkg/a_bg.wasm");
+        assert_eq!(
+            args(path, &WasmOpt::default()).unwrap(),
+            vec!["-Os", "pkg/a_bg.wasm", "-o", "pkg/a_bg.wasm"]
+        );
+
+        let options = WasmOpt {
+            level: Some("z".to_string()),
+            strip: Some(true),
+        };
+        assert_eq!(
+            args(path, &options).unwrap(),
+            vec![
+                "-Oz",
+                "--strip-debug",
+                "--strip-producers",
+                "pkg/a_bg.wasm",
+                "-o",
+                "pkg/a_bg.wasm"
+            ]
+        );
+
+        let options = WasmOpt {
+            level: Some("fast".to_string()),
+            ..WasmOpt::default()
+        };
+        assert!(args(path, &options).is_err());
+    }
+}
@@ -0,0 +1,187 @@
+use std::collections::BTreeSet;
+use std::env;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError};
+use std::time::Duration;
+
+use log::info;
+use notify::{DebouncedEvent, RecommendedWatcher, RecursiveMode, Watcher as _};
+
+use crate::commands::publish::krate::Krate;
+use crate::commands::publish::package::Package;
+use crate::settings::project::{Project, ProjectType};
+
+// How long to wait for a burst of changes, such as a save-all, to settle
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+// Directories that hold build output or dependencies rather than sources
+pub const IGNORED_DIRS: &[&str] = &["worker", "pkg", "node_modules", "target", ".git"];
+
+// The sources of a project: directories watched recursively, minus ignored
+// directories and build output, and single files.
+#[derive(Debug, PartialEq)]
+struct Sources {
+    dirs: Vec<PathBuf>,
+    files: Vec<PathBuf>,
+    output: Vec<PathBuf>,
+}
+
+impl Sources {
+    fn new(project: &Project, root: &Path) -> Result<Sources, failure::Error> {
+        let sources = match project.project_type {
+            ProjectType::JavaScript => {
+                let main = Package::new(&root.to_string_lossy())?.main()?;
+                let main_dir = root
+                    .join(&main)
+                    .parent()
+                    .map(Path::to_path_buf)
+                    .unwrap_or_else(|| root.to_path_buf());
+                Sources {
+                    dirs: vec![main_dir],
+                    files: vec![root.join("package.json")],
+                    output: vec![],
+                }
+            }
+            // the crate may depend on other members of its workspace
+            ProjectType::Rust => {
+                let krate = Krate::new(root, &project.rust_build())?;
+                Sources {
+                    dirs: vec![krate.workspace_root],
+                    files: vec![root.join("worker").join("worker.js")],
+                    output: vec![root.join(project.rust_build().out_dir()), krate.target_dir],
+                }
+            }
+            // everything is in the webpack context, which is the project
+            // root unless configured otherwise
+            ProjectType::Webpack => Sources {
+                dirs: vec![root.to_path_buf()],
+                files: vec![],
+                output: vec![],
+            },
+        };
+
+        Ok(sources)
+    }
+
+    fn contains(&self, path: &Path) -> bool {
+        if self.files.iter().any(|f| f == path) {
+            return true;
+        }
+        if self.output.iter().any(|o| path.starts_with(o)) {
+            return false;
+        }
+
+        self.dirs.iter().any(|dir| match path.strip_prefix(dir) {
+            Ok(relative) => !relative
+                .components()
+                .any(|c| IGNORED_DIRS.iter().any(|i| c.as_os_str() == *i)),
+            Err(_) => false,
+        })
+    }
+}
+
+// Watches the sources of a project for changes.
+pub struct Watcher {
+    // kept alive for as long as changes are waited on
+    _watcher: RecommendedWatcher,
+    rx: Receiver<DebouncedEvent>,
+    sources: Sources,
+}
+
+impl Watcher {
+    pub fn new(project: &Project) -> Result<Watcher, failure::Error> {
+        let root = env::current_dir()?;
+        let sources = Sources::new(project, &root)?;
+
+        let (tx, rx) = channel();
+        let mut watcher = notify::watcher(tx, DEBOUNCE)?;
+        for dir in &sources.dirs {
+            watcher.watch(dir, RecursiveMode::Recursive)?;
+        }
+        // watch the directory of single files, so that they are still
+        // watched once an editor has replaced them
+        let parents: BTreeSet<&Path> = sources.files.iter().filter_map(|f| f.parent()).collect();
+        for parent in parents {
+            if parent.is_dir() {
+                watcher.watch(parent, RecursiveMode::NonRecursive)?;
+            }
+        }
+        info!("watching {:?}", sources);
+
+        Ok(Watcher {
+            _watcher: watcher,
+            rx,
+            sources,
+        })
+    }
+
+    // Blocks until sources have changed, and returns the changed paths once
+    // no more changes have come in for a moment.
+    pub fn wait(&self) -> Result<Vec<PathBuf>, failure::Error> {
+        let mut changed = BTreeSet::new();
+        loop {
+            let event = if changed.is_empty() {
+                self.rx.recv()?
+            } else {
+                match self.rx.recv_timeout(DEBOUNCE) {
+                    Ok(event) => event,
+                    Err(RecvTimeoutError::Timeout) => break,
+                    Err(RecvTimeoutError::Disconnected) => {
+                        failure::bail!("stopped watching for changes")
+                    }
+                }
+            };
+
+            let paths = match event {
+                DebouncedEvent::Create(path)
+                | DebouncedEvent::Write(path)
+                | DebouncedEvent::Remove(path) => vec![path],
+                DebouncedEvent::Rename(from, to) => vec![from, to],
+                DebouncedEvent::Error(e, path) => {
+                    info!("error watching {:?}: {}", path, e);
+                    vec![]
+                }
+                _ => vec![],
+            };
+            changed.extend(paths.into_iter().filter(|p| self.sources.contains(p)));
+        }
+
+        Ok(changed.into_iter().collect())
+    }
+}
+
+// A short description of what changed, for messages.
+pub fn describe(changed: &[PathBuf]) -> String {
+    let root = env::current_dir().unwrap_or_default();
+    let first = changed
+        .first()
+        .map(|p| p.strip_prefix(&root).unwrap_or(p).display().to_string())
+        .unwrap_or_default();
+    match changed.len() {
+        0 | 1 => first,
+        n => format!("{} and {} other files", first, n - 1),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_ignores_build_output_and_dependencies() {
+        let root = Path::new("/project");
+        let sources = Sources {
+            dirs: vec![root.to_path_buf()],
+            files: vec![root.join("worker").join("worker.js")],
+            output: vec![root.join("dist")],
+        };
+
+        assert!(sources.contains(&root.join("index.js")));
+        assert!(sources.contains(&root.join("src").join("lib.js")));
+        assert!(sources.contains(&root.join("worker").join("worker.js")));
+        assert!(!sources.contains(&root.join("worker").join("script.js")));
+        assert!(!sources.contains(&root.join("node_modules").join("a").join("index.js")));
+        assert!(!sources.contains(&root.join("dist").join("a_bg.wasm")));
+        assert!(!sources.contains(Path::new("/elsewhere/index.js")));
+    }
+}
@@ -6,47 +6,91 @@ use std::fs::File;
 use std::io::prelude::*;
 use std::path::{Path, PathBuf};
 
+use crate::commands::build::wasm_opt;
 use crate::commands::build::wranglerjs::output::WranglerjsOutput;
 use crate::settings::binding::Binding;
 use crate::settings::metadata;
+use crate::settings::project::{Project, ProjectType, WasmOpt};
 #[cfg(test)]
 use crate::terminal::message;
 
 // Directory where we should write the {Bundle}. It represents the built
 // artifact.
 const BUNDLE_OUT: &str = "./worker";
+
+// The names the Wasm module is bound to in the script. wranglerjs is told which
+// one to use, while the worker.js of Rust projects refers to `wasm`.
+const WASM_BINDING: &str = "wasmprogram";
+const RUST_WASM_BINDING: &str = "wasm";
+
 pub struct Bundle {
     out: String,
+    wasm_binding: String,
+    // How to optimise the Wasm of the bundle, if at all
+    wasm_opt: Option<WasmOpt>,
 }
 
 // We call a {Bundle} the output of a {Bundler}; representing what {Webpack}
-// produces.
+// produces, or the Rust build puts together.
 impl Bundle {
     pub fn new() -> Bundle {
         Bundle {
             out: BUNDLE_OUT.to_string(),
+            wasm_binding: WASM_BINDING.to_string(),
+            wasm_opt: None,
+        }
+    }
+
+    // The bundle the build of `project` writes
+    pub fn for_project(project: &Project) -> Bundle {
+        let mut bundle = Bundle::new().with_wasm_opt(project.wasm_opt());
+        if let ProjectType::Rust = project.project_type {
+            bundle.wasm_binding = RUST_WASM_BINDING.to_string();
         }
+        bundle
+    }
+
+    pub fn with_wasm_opt(mut self, wasm_opt: Option<WasmOpt>) -> Bundle {
+        self.wasm_opt = wasm_opt;
+        self
     }
 
     #[cfg(test)]
     fn new_at(out: String) -> Bundle {
-        Bundle { out }
+        Bundle {
+            out,
+            wasm_binding: WASM_BINDING.to_string(),
+            wasm_opt: None,
+        }
     }
 
     pub fn write(&self, wranglerjs_output: &WranglerjsOutput) -> Result<(), failure::Error> {
+        let mut script = create_prologue();
+        script += &wranglerjs_output.script;
+
+        let wasm = wranglerjs_output
+            .wasm
+            .as_ref()
+            .map(|encoded_wasm| decode(encoded_wasm).expect("could not decode Wasm in base64"));
+
+        self.write_parts(&script, wasm)
+    }
+
+    // Writes the script, the Wasm if there is any, and the metadata that binds
+    // it to the script.
+    pub fn write_parts(&self, script: &str, wasm: Option<Vec<u8>>) -> Result<(), failure::Error> {
         let bundle_path = Path::new(&self.out);
         if !bundle_path.exists() {
             fs::create_dir(bundle_path)?;
         }
 
         let mut script_file = File::create(self.script_path())?;
-        let mut script = create_prologue();
-        script += &wranglerjs_output.script;
 
-        if let Some(encoded_wasm) = &wranglerjs_output.wasm {
-            let wasm = decode(encoded_wasm).expect("could not decode Wasm in base64");
-            let mut wasm_file = File::create(self.wasm_path())?;
-            wasm_file.write_all(&wasm)?;
+        if let Some(wasm) = wasm {
+            fs::write(self.wasm_path(), &wasm)?;
+            if let Some(options) = &self.wasm_opt {
+                wasm_opt::optimize(Path::new(&self.wasm_path()), options)?;
+            }
         }
 
         script_file.write_all(script.as_bytes())?;
@@ -83,7 +127,15 @@ impl Bundle {
     }
 
     pub fn get_wasm_binding(&self) -> String {
-        "wasmprogram".to_string()
+        self.wasm_binding.clone()
+    }
+
+    pub fn analyze_path(&self) -> String {
+        Path::new(&self.out)
+            .join("analyze.json")
+            .to_str()
+            .unwrap()
+            .to_string()
     }
 
     pub fn script_path(&self) -> String {
@@ -142,8 +194,8 @@ mod tests {
         let wranglerjs_output = WranglerjsOutput {
             errors: vec![],
             script: "".to_string(),
-
             wasm: None,
+            modules: vec![],
         };
         let bundle = Bundle::new_at(out.clone());
 
@@ -164,6 +216,7 @@ mod tests {
             errors: vec![],
             script: "foo".to_string(),
             wasm: None,
+            modules: vec![],
         };
         let bundle = Bundle::new_at(out.clone());
 
@@ -181,6 +234,7 @@ mod tests {
             errors: vec![],
             script: "".to_string(),
             wasm: Some("abc".to_string()),
+            modules: vec![],
         };
         let bundle = Bundle::new_at(out.clone());
 
@@ -198,6 +252,7 @@ mod tests {
             errors: vec![],
             script: "".to_string(),
             wasm: Some("abc".to_string()),
+            modules: vec![],
         };
         let bundle = Bundle::new_at(out.clone());
 
@@ -220,6 +275,7 @@ mod tests {
             errors: vec!["a".to_string(), "b".to_string()],
             script: "".to_string(),
             wasm: None,
+            modules: vec![],
         };
         assert!(wranglerjs_output.has_errors());
         assert!(wranglerjs_output.get_errors() == "a\nb");
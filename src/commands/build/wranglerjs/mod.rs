@@ -12,58 +12,97 @@ use rand::{thread_rng, Rng};
 use std::env;
 use std::fs;
 use std::fs::File;
+use std::io::{BufRead, BufReader};
 use std::iter;
 use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::process::{Command, Stdio};
 
 use crate::settings::project::Project;
 
 use crate::terminal::message;
 
+// What {wranglerjs} prints on stdout in watch mode once it has written a build
+// to its output file
+const BUILD_MARKER: &str = "[wranglerjs] build written";
+
 // Run the underlying {wranglerjs} executable.
 //
 // In Rust we create a virtual file, pass the pass to {wranglerjs}, run the
 // executable and wait for completion. The file will receive the a serialized
 // {WranglerjsOutput} struct.
 // Note that the ability to pass a fd is platform-specific
-pub fn run_build(project: &Project) -> Result<(), failure::Error> {
-    let (mut command, temp_file, bundle) = setup_build(project)?;
+pub fn run_build(project: &Project, analyze: bool) -> Result<WranglerjsOutput, failure::Error> {
+    let (mut command, temp_file, bundle) = setup_build(project, analyze)?;
 
     info!("Running {:?}", command);
 
     let status = command.status()?;
 
     if status.success() {
-        let output = fs::read_to_string(temp_file.clone()).expect("could not retrieve ouput");
+        let output = read_output(&temp_file, &bundle);
+        fs::remove_file(temp_file)?;
+        output
+    } else {
         fs::remove_file(temp_file)?;
+        failure::bail!("failed to execute `{:?}`: exited with {}", command, status)
+    }
+}
 
-        let wranglerjs_output: WranglerjsOutput =
-            serde_json::from_str(&output).expect("could not parse wranglerjs output");
+// Run {wranglerjs} in watch mode, where webpack rebuilds on every change
+// instead of exiting. {on_build} is called with the outcome of each build,
+// written like {run_build} does, until {wranglerjs} exits.
+pub fn run_watch<F>(project: &Project, mut on_build: F) -> Result<(), failure::Error>
+where
+    F: FnMut(Result<WranglerjsOutput, failure::Error>),
+{
+    let (mut command, temp_file, bundle) = setup_build(project, false)?;
+    command.arg("--watch=1").stdout(Stdio::piped());
 
-        if wranglerjs_output.has_errors() {
-            message::user_error(wranglerjs_output.get_errors().as_str());
-            failure::bail!("Webpack returned an error");
+    info!("Running {:?}", command);
+
+    let mut child = command.spawn()?;
+    let stdout = child.stdout.take().expect("wranglerjs stdout is piped");
+    for line in BufReader::new(stdout).lines() {
+        let line = line?;
+        if line == BUILD_MARKER {
+            on_build(read_output(&temp_file, &bundle));
+        } else {
+            // anything else is printed by webpack plugins
+            message::detail(&line);
         }
+    }
 
-        bundle
-            .write(&wranglerjs_output)
-            .expect("could not write bundle to disk");
+    let status = child.wait()?;
+    if temp_file.exists() {
+        fs::remove_file(temp_file)?;
+    }
+    failure::bail!("`{:?}` stopped watching: exited with {}", command, status)
+}
 
-        let msg = format!(
-            "Built successfully, built project size is {}",
-            wranglerjs_output.project_size()
-        );
+// Read the output {wranglerjs} wrote for a build, and write it as a {Bundle}
+// unless webpack reported errors.
+fn read_output(temp_file: &Path, bundle: &Bundle) -> Result<WranglerjsOutput, failure::Error> {
+    let output = fs::read_to_string(temp_file)
+        .map_err(|e| failure::format_err!("could not retrieve wranglerjs output: {}", e))?;
 
-        message::success(&msg);
-        Ok(())
-    } else {
-        fs::remove_file(temp_file)?;
-        failure::bail!("failed to execute `{:?}`: exited with {}", command, status)
+    let wranglerjs_output: WranglerjsOutput = serde_json::from_str(&output)
+        .map_err(|e| failure::format_err!("could not parse wranglerjs output: {}", e))?;
+
+    if wranglerjs_output.has_errors() {
+        message::user_error(wranglerjs_output.get_errors().as_str());
+        failure::bail!("Webpack returned an error");
     }
+
+    bundle.write(&wranglerjs_output)?;
+
+    Ok(wranglerjs_output)
 }
 
 //setup a build to run wranglerjs, return the command, the ipc temp file, and the bundle
-fn setup_build(project: &Project) -> Result<(Command, PathBuf, Bundle), failure::Error> {
+fn setup_build(
+    project: &Project,
+    analyze: bool,
+) -> Result<(Command, PathBuf, Bundle), failure::Error> {
     for tool in &["node", "npm"] {
         env_dep_installed(tool)?;
     }
@@ -90,10 +129,15 @@ fn setup_build(project: &Project) -> Result<(Command, PathBuf, Bundle), failure:
         temp_file.clone().to_str().unwrap().to_string()
     ));
 
-    let bundle = Bundle::new();
+    let bundle = Bundle::for_project(project);
 
     command.arg(format!("--wasm-binding={}", bundle.get_wasm_binding()));
 
+    if analyze {
+        command.arg("--analyze=1");
+    }
+
+    let webpack = project.webpack_build();
     let webpack_config_path = PathBuf::from(
         &project
             .webpack_config
@@ -119,6 +163,29 @@ fn setup_build(project: &Project) -> Result<(Command, PathBuf, Bundle), failure:
             "--webpack-config={}",
             &webpack_config_path.to_str().unwrap().to_string()
         ));
+        // which of several exported configurations to build
+        if let Some(config) = &webpack.config {
+            command.arg(format!("--use-config={}", config));
+        }
+    }
+
+    // the mode overrides the one of the config, and loaders and plugins
+    // usually go by NODE_ENV
+    if let Some(mode) = &webpack.mode {
+        match mode.as_str() {
+            "development" | "production" => {
+                command.env("NODE_ENV", mode);
+            }
+            "none" => {}
+            _ => failure::bail!(
+                "Unknown webpack mode `{}`; use development, production or none",
+                mode
+            ),
+        }
+        command.arg(format!("--mode={}", mode));
+    }
+    if let Some(env) = &webpack.env {
+        command.arg(format!("--env={}", serde_json::to_string(env)?));
     }
 
     Ok((command, temp_file, bundle))
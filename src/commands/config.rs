@@ -11,8 +11,6 @@ pub fn global_config(email: &str, api_key: &str) -> Result<(), failure::Error> {
         api_key: api_key.to_string(),
     };
 
-    let toml = toml::to_string(&s)?;
-
     let config_dir = Path::new(&dirs::home_dir().unwrap_or_else(|| {
         panic!(
             "{0} could not determine home directory. {0}",
@@ -24,6 +22,18 @@ pub fn global_config(email: &str, api_key: &str) -> Result<(), failure::Error> {
     fs::create_dir_all(&config_dir)?;
 
     let config_file = config_dir.join("default.toml");
+
+    // keep anything else already configured, such as preferences
+    let mut config = fs::read_to_string(&config_file)
+        .ok()
+        .and_then(|existing| existing.parse::<toml::Value>().ok())
+        .and_then(|existing| existing.as_table().cloned())
+        .unwrap_or_default();
+    if let toml::Value::Table(user) = toml::Value::try_from(&s)? {
+        config.extend(user);
+    }
+
+    let toml = toml::to_string(&config)?;
     fs::write(&config_file, &toml)?;
 
     message::success(&format!(
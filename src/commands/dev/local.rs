@@ -0,0 +1,104 @@
+use std::fs;
+use std::path::Path;
+use std::process::{Child, Command};
+
+use log::info;
+
+use crate::commands;
+use crate::commands::build::watch::{self, Watcher};
+use crate::commands::build::BuildOptions;
+use crate::commands::dev::{self, DevOptions};
+use crate::commands::publish;
+use crate::settings::project::Project;
+use crate::terminal::message;
+
+// The Service Worker emulator, shipped with wranglerjs. It is embedded so
+// that running locally never needs a download.
+const EMULATOR: &str = include_str!("../../../wranglerjs/local.js");
+
+// Where the parts that would be uploaded are written for the emulator, one
+// file per part, named after it
+const LOCAL_DIR: &str = "./worker/local";
+// Where KV namespaces are stored, one directory per namespace
+const KV_DIR: &str = "./worker/kv";
+
+// Runs the project in the emulator, without the preview service, and
+// restarts it with a new build whenever the sources change.
+pub fn dev(project: &Project, options: &DevOptions) -> Result<(), failure::Error> {
+    let node = match which::which("node") {
+        Ok(node) => node,
+        Err(_) => failure::bail!("You need to install node to run your worker locally"),
+    };
+    let emulator = Path::new(LOCAL_DIR).join("emulator.js");
+
+    write_bundle(project)?;
+    fs::write(&emulator, EMULATOR)?;
+
+    let command = || {
+        let mut command = Command::new(&node);
+        command
+            .arg(&emulator)
+            .arg(format!(
+                "--bundle={}",
+                Path::new(LOCAL_DIR).join("parts").display()
+            ))
+            .arg(format!("--kv-dir={}", KV_DIR))
+            .arg(format!(
+                "--kv={}",
+                project.kv_namespaces.clone().unwrap_or_default().join(",")
+            ))
+            .arg(format!("--host={}", options.host))
+            .arg(format!("--port={}", options.port));
+        command
+    };
+
+    let watcher = Watcher::new(project)?;
+    let mut emulator = start(command())?;
+    dev::replay_when_listening(options)?;
+    loop {
+        let changed = watcher.wait()?;
+        message::working(&format!(
+            "{} changed, restarting your worker",
+            watch::describe(&changed)
+        ));
+
+        match write_bundle(project) {
+            Ok(()) => {
+                stop(&mut emulator);
+                emulator = start(command())?;
+            }
+            Err(e) => message::user_error(&e.to_string()),
+        }
+    }
+}
+
+// Builds the project and writes the parts that would be uploaded.
+fn write_bundle(project: &Project) -> Result<(), failure::Error> {
+    commands::build(project, &BuildOptions::default())?;
+    let form = publish::build_script_upload_form(project)?;
+    publish::validate_wasm(&form)?;
+
+    let parts_dir = Path::new(LOCAL_DIR).join("parts");
+    if parts_dir.exists() {
+        fs::remove_dir_all(&parts_dir)?;
+    }
+    fs::create_dir_all(&parts_dir)?;
+    for part in &form.parts {
+        fs::write(parts_dir.join(&part.name), &part.contents)?;
+    }
+
+    Ok(())
+}
+
+fn start(mut command: Command) -> Result<Child, failure::Error> {
+    info!("Running {:?}", command);
+    let child = command.spawn()?;
+    message::preview("Running your worker locally");
+    Ok(child)
+}
+
+fn stop(emulator: &mut Child) {
+    if let Err(e) = emulator.kill().and_then(|_| emulator.wait()) {
+        info!("could not stop the emulator: {}", e);
+    }
+}
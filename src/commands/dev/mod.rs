@@ -0,0 +1,225 @@
+mod local;
+
+use std::net::TcpStream;
+use std::sync::{Arc, RwLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use log::info;
+use reqwest::Method;
+use tiny_http::{Header, Request, Response, Server, StatusCode};
+use uuid::Uuid;
+
+use crate::commands::build::watch::{self, Watcher};
+use crate::commands::build::BuildOptions;
+use crate::commands::publish::preview::{self, PreviewOptions, ReplayOptions, Target};
+use crate::http;
+use crate::settings::project::Project;
+use crate::terminal::message;
+
+// Headers that only concern a single connection, and are not forwarded
+const HOP_BY_HOP: &[&str] = &[
+    "connection",
+    "content-length",
+    "host",
+    "keep-alive",
+    "proxy-connection",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+];
+
+// How long the worker has to start listening before requests are replayed
+const LISTEN_TIMEOUT: Duration = Duration::from_secs(30);
+
+// Where `wrangler dev` listens, and how it runs the worker.
+#[derive(Debug)]
+pub struct DevOptions {
+    pub host: String,
+    pub port: u16,
+    // Run the worker in a local emulator rather than on the preview service
+    pub local: bool,
+    // Requests to replay once the worker is served
+    pub replay: Option<ReplayOptions>,
+}
+
+// Serves the project on a local address by forwarding every request to its
+// preview, which is updated whenever the sources change.
+pub fn dev(project: &Project, options: &DevOptions) -> Result<(), failure::Error> {
+    if options.local {
+        return local::dev(project, options);
+    }
+
+    let client = http::proxy_client();
+    let script_id = Arc::new(RwLock::new(preview::upload(
+        &client,
+        project,
+        None,
+        &BuildOptions::default(),
+    )?));
+    // the session is kept across uploads, like `preview --watch` does
+    let session = Uuid::new_v4().to_simple().to_string();
+
+    let server = match Server::http((options.host.as_str(), options.port)) {
+        Ok(server) => server,
+        Err(e) => failure::bail!(
+            "Could not listen on {}:{}: {}",
+            options.host,
+            options.port,
+            e
+        ),
+    };
+
+    update_on_change(project, &client, &script_id)?;
+    replay_when_listening(options)?;
+
+    message::preview(&format!(
+        "Serving your worker on http://{}:{}",
+        options.host, options.port
+    ));
+    for request in server.incoming_requests() {
+        let client = client.clone();
+        let script_id = script_id.read().expect("preview ID lock").clone();
+        let session = session.clone();
+        thread::spawn(move || handle(&client, request, &script_id, &session));
+    }
+
+    Ok(())
+}
+
+// Rebuilds and updates the preview in the background whenever the sources
+// change. Requests keep going to the previous preview until it is updated.
+fn update_on_change(
+    project: &Project,
+    client: &reqwest::Client,
+    script_id: &Arc<RwLock<String>>,
+) -> Result<(), failure::Error> {
+    let watcher = Watcher::new(project)?;
+    let project = project.clone();
+    let client = client.clone();
+    let script_id = Arc::clone(script_id);
+
+    thread::spawn(move || loop {
+        let changed = match watcher.wait() {
+            Ok(changed) => changed,
+            Err(e) => {
+                message::user_error(&format!("Stopped watching for changes: {}", e));
+                return;
+            }
+        };
+        message::working(&format!(
+            "{} changed, updating the preview",
+            watch::describe(&changed)
+        ));
+
+        let current = script_id.read().expect("preview ID lock").clone();
+        match preview::upload(&client, &project, Some(&current), &BuildOptions::default()) {
+            Ok(id) => {
+                *script_id.write().expect("preview ID lock") = id;
+                message::success("Updated the preview");
+            }
+            Err(e) => message::user_error(&e.to_string()),
+        }
+    });
+
+    Ok(())
+}
+
+// Replays the requests of the HAR file, if there is one, against the local
+// address as soon as it accepts connections.
+pub fn replay_when_listening(options: &DevOptions) -> Result<(), failure::Error> {
+    let replay = match &options.replay {
+        Some(replay) => replay.clone(),
+        None => return Ok(()),
+    };
+    let address = format!("{}:{}", options.host, options.port);
+    let target = Target::url(&format!("http://{}", address))?;
+
+    thread::spawn(move || {
+        let start = Instant::now();
+        while TcpStream::connect(&address).is_err() {
+            if start.elapsed() > LISTEN_TIMEOUT {
+                message::user_error(&format!(
+                    "Not replaying {}, nothing is listening on {}",
+                    replay.har, address
+                ));
+                return;
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+
+        let client = http::proxy_client();
+        if let Err(e) = preview::replay(&client, &target, &replay, &PreviewOptions::default()) {
+            message::user_error(&e.to_string());
+        }
+    });
+
+    Ok(())
+}
+
+fn handle(client: &reqwest::Client, mut request: Request, script_id: &str, session: &str) {
+    let line = format!("{} {}", request.method(), request.url());
+
+    let result = match forward(client, &mut request, script_id, session) {
+        Ok(res) => {
+            message::preview(&format!("{} {}", line, res.status()));
+            request.respond(proxied(res))
+        }
+        Err(e) => {
+            message::user_error(&format!("{} failed: {}", line, e));
+            request.respond(Response::from_string(e.to_string()).with_status_code(502))
+        }
+    };
+    if let Err(e) = result {
+        info!("could not respond to {}: {}", line, e);
+    }
+}
+
+// Sends the request to the preview, as if it were made to the local address.
+fn forward(
+    client: &reqwest::Client,
+    request: &mut Request,
+    script_id: &str,
+    session: &str,
+) -> Result<reqwest::Response, failure::Error> {
+    let method = Method::from_bytes(request.method().as_str().as_bytes())?;
+    let host = request
+        .headers()
+        .iter()
+        .find(|h| h.field.equiv("host"))
+        .map(|h| h.value.to_string())
+        .unwrap_or_else(|| "localhost".to_string());
+
+    let mut cookie = preview::cookie(script_id, session, false, &host);
+    let mut req = client.request(
+        method,
+        &format!("{}{}", preview::PREVIEW_ADDRESS, request.url()),
+    );
+    for header in request.headers() {
+        let name = header.field.as_str().as_str();
+        if header.field.equiv("cookie") {
+            cookie = format!("{}; {}", header.value, cookie);
+        } else if !HOP_BY_HOP.iter().any(|h| header.field.equiv(h)) {
+            req = req.header(name, header.value.as_str());
+        }
+    }
+
+    let mut body = vec![];
+    request.as_reader().read_to_end(&mut body)?;
+
+    Ok(req.header("Cookie", cookie).body(body).send()?)
+}
+
+fn proxied(res: reqwest::Response) -> Response<reqwest::Response> {
+    let headers = res
+        .headers()
+        .iter()
+        .filter(|(name, _)| !HOP_BY_HOP.contains(&name.as_str()))
+        .filter_map(|(name, value)| Header::from_bytes(name.as_str(), value.as_bytes()).ok())
+        .collect();
+    let status = StatusCode(res.status().as_u16());
+    let length = res.content_length().map(|l| l as usize);
+
+    Response::new(status, headers, res, length, None)
+}
@@ -2,22 +2,30 @@ use std::process::Command;
 
 use log::info;
 
+pub mod bench;
 pub mod build;
 pub mod config;
+pub mod dev;
 pub mod generate;
 pub mod init;
 pub mod publish;
 pub mod subdomain;
+pub mod test;
 pub mod whoami;
 
 pub use self::config::global_config;
-pub use build::build;
+pub use bench::{bench, BenchOptions};
+pub use build::{build, BuildOptions};
+pub use dev::{dev, DevOptions};
 pub use generate::generate;
 pub use init::init;
 pub use publish::preview::preview;
-pub use publish::preview::HTTPMethod;
+pub use publish::preview::{
+    read_body, CompareOptions, HTTPMethod, PreviewOptions, PreviewRequest, ReplayOptions,
+};
 pub use publish::publish;
 pub use subdomain::subdomain;
+pub use test::{test, TestOptions};
 pub use whoami::whoami;
 
 /// Run the given command and return its stdout.
@@ -0,0 +1,166 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use log::info;
+use serde::{Deserialize, Serialize};
+
+use crate::settings::project::Project;
+
+use super::script_upload_form::ScriptUploadForm;
+
+// Where the state of the last successful publish is recorded, unless the
+// project sets `deploy_state` in its wrangler.toml.
+const DEPLOY_STATE_PATH: &str = "./worker/deploy_state.json";
+
+// A fingerprint of everything `publish` sends to the Workers API for a
+// script. If it matches the one recorded after the previous publish, the
+// script upload can be skipped.
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+pub struct DeployState {
+    account_id: String,
+    script: String,
+    // sha256 of each part of the multipart upload, keyed by part name
+    parts: BTreeMap<String, String>,
+    routes: Vec<String>,
+    bindings: Vec<String>,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+impl DeployState {
+    pub fn new(
+        project: &Project,
+        release: bool,
+        form: &ScriptUploadForm,
+    ) -> Result<DeployState, failure::Error> {
+        let parts = form
+            .parts
+            .iter()
+            .map(|part| (part.name.clone(), part.hash()))
+            .collect();
+
+        Ok(DeployState {
+            account_id: project.account_id.clone(),
+            script: project.name.clone(),
+            parts,
+            routes: routes(project, release),
+            bindings: project.kv_namespaces.clone().unwrap_or_default(),
+            path: state_path(project),
+        })
+    }
+
+    // Whether the previously recorded state matches this one. A missing or
+    // unreadable state file is treated as a change.
+    pub fn is_unchanged(&self) -> bool {
+        match fs::read_to_string(&self.path) {
+            Ok(contents) => match serde_json::from_str::<DeployState>(&contents) {
+                Ok(mut previous) => {
+                    previous.path = self.path.clone();
+                    previous == *self
+                }
+                Err(e) => {
+                    info!("could not parse {}: {}", self.path.display(), e);
+                    false
+                }
+            },
+            Err(_) => false,
+        }
+    }
+
+    pub fn save(&self) -> Result<(), failure::Error> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        info!("Writing deploy state to {}", self.path.display());
+        fs::write(&self.path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+fn state_path(project: &Project) -> PathBuf {
+    let path = project
+        .deploy_state
+        .clone()
+        .unwrap_or_else(|| DEPLOY_STATE_PATH.to_string());
+    Path::new(&path).to_path_buf()
+}
+
+fn routes(project: &Project, release: bool) -> Vec<String> {
+    let mut routes = vec![];
+    if release {
+        if let Some(route) = &project.route {
+            routes.push(route.clone());
+        }
+    } else if !project.private.unwrap_or(false) {
+        routes.push("workers.dev".to_string());
+    }
+
+    if let Some(extra) = &project.routes {
+        let mut extra: Vec<String> = extra.iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+        extra.sort();
+        routes.extend(extra);
+    }
+
+    routes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::publish::script_upload_form::UploadPart;
+    use crate::settings::project::ProjectType;
+    use std::env;
+
+    fn project(deploy_state: &Path) -> Project {
+        Project {
+            name: "test".to_string(),
+            project_type: ProjectType::Webpack,
+            zone_id: None,
+            private: None,
+            webpack_config: None,
+            account_id: "account".to_string(),
+            route: None,
+            routes: None,
+            kv_namespaces: None,
+            deploy_state: Some(deploy_state.to_str().unwrap().to_string()),
+            plan: None,
+            size_limits: None,
+            build: None,
+        }
+    }
+
+    fn form(script: &str) -> ScriptUploadForm {
+        ScriptUploadForm {
+            parts: vec![UploadPart {
+                name: "script".to_string(),
+                file_name: "script.js".to_string(),
+                mime: "application/javascript".to_string(),
+                contents: script.as_bytes().to_vec(),
+            }],
+        }
+    }
+
+    #[test]
+    fn it_detects_unchanged_uploads() {
+        let path =
+            env::temp_dir().join(format!("wrangler-deploy-state-{}.json", std::process::id()));
+        if path.exists() {
+            fs::remove_file(&path).unwrap();
+        }
+        let project = project(&path);
+
+        let state = DeployState::new(&project, false, &form("foo")).unwrap();
+        assert!(!state.is_unchanged());
+        state.save().unwrap();
+        assert!(state.is_unchanged());
+
+        let changed = DeployState::new(&project, false, &form("bar")).unwrap();
+        assert!(!changed.is_unchanged());
+
+        let released = DeployState::new(&project, true, &form("foo")).unwrap();
+        assert!(!released.is_unchanged());
+
+        fs::remove_file(&path).unwrap();
+    }
+}
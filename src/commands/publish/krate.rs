@@ -1,32 +1,252 @@
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::process::Command;
 
+use log::info;
 use serde::{self, Deserialize};
 
-#[derive(Debug, Deserialize)]
+use crate::settings::project::RustBuild;
+
+// The crate of a Rust worker, which may be a member of a Cargo workspace.
+#[derive(Debug, PartialEq)]
 pub struct Krate {
     pub name: String,
+    // The name of the files wasm-bindgen generates, after the library target
+    pub out_name: String,
+    // The directory of the crate's Cargo.toml
+    pub dir: PathBuf,
+    // Where the workspace, and its Cargo.lock, is
+    pub workspace_root: PathBuf,
+    // Where cargo builds to
+    pub target_dir: PathBuf,
+}
+
+// What `cargo metadata --no-deps` says about the packages of a workspace
+#[derive(Debug, Deserialize)]
+struct Metadata {
+    packages: Vec<MetadataPackage>,
+    workspace_root: PathBuf,
+    target_directory: PathBuf,
 }
 
 #[derive(Debug, Deserialize)]
-struct KrateManifest {
-    pub package: Krate,
+struct MetadataPackage {
+    name: String,
+    manifest_path: PathBuf,
+    targets: Vec<MetadataTarget>,
+}
+
+#[derive(Debug, Deserialize)]
+struct MetadataTarget {
+    name: String,
+    crate_types: Vec<String>,
 }
 
 impl Krate {
-    pub fn new(krate_path: &str) -> Result<Krate, failure::Error> {
-        let manifest_path = Path::new(krate_path).join("Cargo.toml");
-        if !manifest_path.is_file() {
+    // The crate of the project at `root` that `[build.rust]` names by `crate`
+    // or `path`, which is relative to `root`. Without either, this is the only
+    // package of the workspace, or the package at the root of it.
+    pub fn new(root: &Path, rust: &RustBuild) -> Result<Krate, failure::Error> {
+        // a crate named by its path may be outside of the project, in a
+        // workspace of its own
+        let manifest = match &rust.path {
+            Some(path) => root.join(path).join("Cargo.toml"),
+            None => root.join("Cargo.toml"),
+        };
+        if !manifest.is_file() {
             failure::bail!(
                 "crate directory is missing a `Cargo.toml` file; is `{}` the \
                  wrong directory?",
-                krate_path
+                manifest.parent().unwrap_or(root).display()
             )
         }
 
-        let cargo_toml: String = fs::read_to_string(manifest_path)?.parse()?;
-        let krate: KrateManifest = toml::from_str(&cargo_toml)?;
+        let mut command = Command::new("cargo");
+        command
+            .args(["metadata", "--format-version", "1", "--no-deps"])
+            .arg("--manifest-path")
+            .arg(&manifest)
+            .current_dir(root);
+        info!("Running {:?}", command);
+        let output = command.output()?;
+        if !output.status.success() {
+            failure::bail!(
+                "`cargo metadata` failed: {}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            )
+        }
+        let metadata: Metadata = serde_json::from_slice(&output.stdout)?;
+
+        select(metadata, &fs::canonicalize(root)?, rust)
+    }
+}
+
+fn select(metadata: Metadata, root: &Path, rust: &RustBuild) -> Result<Krate, failure::Error> {
+    let packages = metadata.packages;
+    // directories are compared as canonical paths, which `root` already is
+    let dir_of = |package: &MetadataPackage| {
+        let dir = package
+            .manifest_path
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+        fs::canonicalize(&dir).unwrap_or(dir)
+    };
+    let names = packages
+        .iter()
+        .map(|p| format!("`{}`", p.name))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    let package = if let Some(name) = &rust.krate {
+        match packages.into_iter().find(|p| &p.name == name) {
+            Some(package) => package,
+            None => failure::bail!(
+                "There is no crate `{}` in your Cargo workspace; it has {}",
+                name,
+                names
+            ),
+        }
+    } else if let Some(path) = &rust.path {
+        let dir = fs::canonicalize(root.join(path))
+            .map_err(|e| failure::format_err!("Could not find the crate at `{}`: {}", path, e))?;
+        match packages.into_iter().find(|p| dir_of(p) == dir) {
+            Some(package) => package,
+            None => failure::bail!(
+                "There is no crate at `{}` in your Cargo workspace; it has {}",
+                path,
+                names
+            ),
+        }
+    } else if packages.len() == 1 {
+        packages.into_iter().next().expect("there is one package")
+    } else {
+        match packages.into_iter().find(|p| dir_of(p) == root) {
+            Some(package) => package,
+            None => failure::bail!(
+                "Your Cargo workspace has several crates ({}); set `crate` or `path` in the `[build.rust]` section of your `wrangler.toml` to the one of your worker",
+                names
+            ),
+        }
+    };
+
+    // wasm-bindgen names its output after the library, which may not be
+    // named after the package
+    let lib = package
+        .targets
+        .iter()
+        .find(|t| t.crate_types.iter().any(|c| c == "cdylib"))
+        .map(|t| t.name.clone())
+        .unwrap_or_else(|| package.name.clone());
+
+    Ok(Krate {
+        dir: dir_of(&package),
+        out_name: lib.replace("-", "_"),
+        name: package.name,
+        workspace_root: metadata.workspace_root,
+        target_dir: metadata.target_directory,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn package(name: &str, dir: &str, lib: &str) -> MetadataPackage {
+        MetadataPackage {
+            name: name.to_string(),
+            manifest_path: Path::new(dir).join("Cargo.toml"),
+            targets: vec![MetadataTarget {
+                name: lib.to_string(),
+                crate_types: vec!["cdylib".to_string(), "rlib".to_string()],
+            }],
+        }
+    }
+
+    fn metadata(packages: Vec<MetadataPackage>, root: &str) -> Metadata {
+        Metadata {
+            packages,
+            workspace_root: PathBuf::from(root),
+            target_directory: Path::new(root).join("target"),
+        }
+    }
+
+    fn workspace() -> Metadata {
+        metadata(
+            vec![
+                package("shared", "/ws/shared", "shared"),
+                package("api-worker", "/ws/workers/api", "api-worker"),
+            ],
+            "/ws",
+        )
+    }
+
+    #[test]
+    fn it_selects_a_workspace_member() {
+        let root = Path::new("/ws");
+
+        let rust = RustBuild {
+            krate: Some("api-worker".to_string()),
+            ..RustBuild::default()
+        };
+        assert_eq!(
+            select(workspace(), root, &rust).unwrap(),
+            Krate {
+                name: "api-worker".to_string(),
+                out_name: "api_worker".to_string(),
+                dir: PathBuf::from("/ws/workers/api"),
+                workspace_root: PathBuf::from("/ws"),
+                target_dir: PathBuf::from("/ws/target"),
+            }
+        );
+
+        // a workspace of several crates needs to be told which one to build
+        assert!(select(workspace(), root, &RustBuild::default()).is_err());
+
+        let rust = RustBuild {
+            krate: Some("web".to_string()),
+            ..RustBuild::default()
+        };
+        assert!(select(workspace(), root, &rust).is_err());
+    }
+
+    #[test]
+    fn it_finds_a_crate_outside_of_the_project() {
+        let dir = std::env::temp_dir().join(format!("wrangler-krate-{}", std::process::id()));
+        let krate_dir = dir.join("crates").join("worker");
+        fs::create_dir_all(dir.join("project")).unwrap();
+        fs::create_dir_all(krate_dir.join("src")).unwrap();
+        fs::write(
+            krate_dir.join("Cargo.toml"),
+            "[package]\nname = \"worker\"\nversion = \"0.1.0\"\n\n[workspace]\n",
+        )
+        .unwrap();
+        fs::write(krate_dir.join("src").join("lib.rs"), "").unwrap();
+
+        let rust = RustBuild {
+            path: Some("../crates/worker".to_string()),
+            ..RustBuild::default()
+        };
+        let krate = Krate::new(&dir.join("project"), &rust).unwrap();
+        assert_eq!(krate.name, "worker");
+        assert_eq!(krate.dir, fs::canonicalize(&krate_dir).unwrap());
+
+        // without a path, the crate is the one of the project
+        assert!(Krate::new(&dir.join("project"), &RustBuild::default()).is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
 
-        Ok(krate.package)
+    #[test]
+    fn it_selects_the_only_package() {
+        let packages = vec![package("my-worker", "/w", "worker_lib")];
+        let krate = select(
+            metadata(packages, "/w"),
+            Path::new("/w"),
+            &RustBuild::default(),
+        )
+        .unwrap();
+        assert_eq!(krate.name, "my-worker");
+        assert_eq!(krate.out_name, "worker_lib");
     }
 }
@@ -1,31 +1,80 @@
-mod krate;
+mod deploy_state;
+pub mod krate;
 pub mod package;
 pub mod preview;
 mod route;
-mod script_upload_form;
+pub mod script_upload_form;
+mod validate_wasm;
 
+use deploy_state::DeployState;
 use package::Package;
 use route::Route;
-use script_upload_form::build_script_upload_form;
+pub use script_upload_form::{build_script_upload_form, ScriptUploadForm};
+pub use validate_wasm::validate_wasm;
 
 use log::info;
 
 use std::collections::HashMap;
 
 use crate::commands;
+use crate::commands::build::size::ProjectSize;
+use crate::commands::build::BuildOptions;
 use crate::commands::subdomain::Subdomain;
 use crate::http;
 use crate::settings::global_user::GlobalUser;
 use crate::settings::project::Project;
 use crate::terminal::message;
 
-pub fn publish(user: &GlobalUser, project: &Project, release: bool) -> Result<(), failure::Error> {
-    info!("release = {}", release);
+pub fn publish(
+    user: &GlobalUser,
+    project: &Project,
+    release: bool,
+    force: bool,
+    build: &BuildOptions,
+) -> Result<(), failure::Error> {
+    info!("release = {}, force = {}", release, force);
 
     validate_project(project, release)?;
-    commands::build(&project)?;
+    commands::build(&project, build)?;
+
+    // everything that can fail the publish is checked before anything is
+    // created or uploaded
+    let script_upload_form = build_script_upload_form(project)?;
+    validate_wasm(&script_upload_form)?;
+
+    let size = ProjectSize::new(&script_upload_form);
+    let size_limit = project.size_limit();
+    if size.exceeds(size_limit) {
+        if force {
+            message::user_error(&format!(
+                "Your script is {}; uploading anyway because `--force` was passed.",
+                size.message(size_limit)
+            ));
+        } else {
+            failure::bail!(
+                "Your script is {}. Pass `--force` to try to publish it anyway.",
+                size.message(size_limit)
+            );
+        }
+    }
+
     create_kv_namespaces(user, &project)?;
-    publish_script(&user, &project, release)?;
+
+    let deploy_state = DeployState::new(project, release, &script_upload_form)?;
+    if !force && deploy_state.is_unchanged() {
+        message::info("Your script is unchanged since the last publish, skipping upload. Pass `--force` to upload it anyway.");
+    } else {
+        publish_script(&user, &project, script_upload_form)?;
+    }
+
+    if !release {
+        let private = project.private.unwrap_or(false);
+        if !private {
+            info!("--release not passed, publishing to subdomain");
+            make_public_on_subdomain(project, user)?;
+        }
+    }
+
     if release {
         info!("release mode detected, making a route...");
         let route = Route::new(&project)?;
@@ -38,6 +87,7 @@ pub fn publish(user: &GlobalUser, project: &Project, release: bool) -> Result<()
     } else {
         message::success("Success! Your worker was successfully published.");
     }
+    deploy_state.save()?;
     Ok(())
 }
 
@@ -81,7 +131,7 @@ pub fn create_kv_namespaces(user: &GlobalUser, project: &Project) -> Result<(),
 fn publish_script(
     user: &GlobalUser,
     project: &Project,
-    release: bool,
+    script_upload_form: ScriptUploadForm,
 ) -> Result<(), failure::Error> {
     let worker_addr = format!(
         "https://api.cloudflare.com/client/v4/accounts/{}/workers/scripts/{}",
@@ -90,11 +140,9 @@ fn publish_script(
 
     let client = http::auth_client(user);
 
-    let script_upload_form = build_script_upload_form(project)?;
-
     let mut res = client
         .put(&worker_addr)
-        .multipart(script_upload_form)
+        .multipart(script_upload_form.into_form()?)
         .send()?;
 
     if res.status().is_success() {
@@ -107,14 +155,6 @@ fn publish_script(
         )
     }
 
-    if !release {
-        let private = project.private.unwrap_or(false);
-        if !private {
-            info!("--release not passed, publishing to subdomain");
-            make_public_on_subdomain(project, user)?;
-        }
-    }
-
     Ok(())
 }
 
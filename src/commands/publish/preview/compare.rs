@@ -0,0 +1,308 @@
+use std::collections::BTreeMap;
+use std::fs;
+
+use serde::Serialize;
+
+use super::{PreviewRequest, PreviewResponse, Target};
+use crate::terminal::message;
+
+// Headers set by the edge rather than by the worker, which differ between
+// any two responses
+const IGNORED_HEADERS: &[&str] = &[
+    "age",
+    "alt-svc",
+    "cf-cache-status",
+    "cf-ray",
+    "date",
+    "expect-ct",
+    "expires",
+    "nel",
+    "report-to",
+    "server",
+];
+
+// What the preview is compared with, and on which requests.
+#[derive(Debug, Default)]
+pub struct CompareOptions {
+    // The base URL of the worker in production
+    pub url: String,
+    // A file of paths to request, one per line, instead of the URL of the
+    // request
+    pub paths: Option<String>,
+    // More headers to leave out of the comparison
+    pub ignore_headers: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct Comparison {
+    method: String,
+    url: String,
+    differences: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct Report {
+    requests: Vec<Comparison>,
+    different: usize,
+}
+
+// Sends the request, or one request for each of the paths, to both the
+// preview and production, and reports where their responses differ.
+pub fn compare(
+    client: &reqwest::Client,
+    request: &PreviewRequest,
+    preview: &Target,
+    options: &CompareOptions,
+    json: bool,
+) -> Result<(), failure::Error> {
+    let production = Target::url(&options.url)?;
+    let urls = match &options.paths {
+        Some(file) => read_paths(file)?,
+        None => vec![request.path_and_query()],
+    };
+    let ignored: Vec<String> = IGNORED_HEADERS
+        .iter()
+        .map(|h| h.to_string())
+        .chain(options.ignore_headers.iter().map(|h| h.to_lowercase()))
+        .collect();
+
+    message::working(&format!(
+        "Comparing {} requests with {}",
+        urls.len(),
+        options.url
+    ));
+    let mut requests = vec![];
+    for url in urls {
+        let request = request.at(&url)?;
+        let name = format!("{} {}", request.method, request.path_and_query());
+
+        let differences = match (
+            preview.send(client, &request),
+            production.send(client, &request),
+        ) {
+            (Ok(preview), Ok(production)) => differences(&preview, &production, &ignored),
+            (preview, production) => preview
+                .err()
+                .map(|e| format!("preview: request failed: {}", e))
+                .into_iter()
+                .chain(
+                    production
+                        .err()
+                        .map(|e| format!("production: request failed: {}", e)),
+                )
+                .collect(),
+        };
+
+        if differences.is_empty() {
+            message::success(&format!("{}: same response", name));
+        } else {
+            message::user_error(&format!("{}: different responses", name));
+            for difference in &differences {
+                message::detail(&format!("    {}", difference));
+            }
+        }
+        requests.push(Comparison {
+            method: request.method.to_string(),
+            url: request.path_and_query(),
+            differences,
+        });
+    }
+
+    let report = Report {
+        different: requests
+            .iter()
+            .filter(|r| !r.differences.is_empty())
+            .count(),
+        requests,
+    };
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    }
+
+    if report.different > 0 {
+        failure::bail!(
+            "{} of {} requests got a different response from production",
+            report.different,
+            report.requests.len()
+        );
+    }
+    message::success("The preview responded like production to every request");
+
+    Ok(())
+}
+
+// The paths in `file`, skipping blank lines and `#` comments.
+fn read_paths(file: &str) -> Result<Vec<String>, failure::Error> {
+    let contents = match fs::read_to_string(file) {
+        Ok(contents) => contents,
+        Err(e) => failure::bail!("Could not read the paths in {}: {}", file, e),
+    };
+    let paths: Vec<String> = contents
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| line.to_string())
+        .collect();
+    if paths.is_empty() {
+        failure::bail!("There are no paths in {}", file);
+    }
+
+    Ok(paths)
+}
+
+// Where the responses differ, in status, in headers other than `ignored`, or
+// in body.
+fn differences(
+    preview: &PreviewResponse,
+    production: &PreviewResponse,
+    ignored: &[String],
+) -> Vec<String> {
+    let mut differences = vec![];
+
+    if preview.status != production.status {
+        differences.push(format!(
+            "status: preview {}, production {}",
+            preview.status_line(),
+            production.status_line()
+        ));
+    }
+
+    let preview_headers = headers(preview, ignored);
+    let production_headers = headers(production, ignored);
+    let mut names: Vec<&String> = preview_headers
+        .keys()
+        .chain(production_headers.keys())
+        .collect();
+    names.sort();
+    names.dedup();
+    for name in names {
+        let (a, b) = (preview_headers.get(name), production_headers.get(name));
+        if a != b {
+            let show = |v: Option<&String>| v.map_or("(none)".to_string(), |v| format!("{:?}", v));
+            differences.push(format!(
+                "header {}: preview {}, production {}",
+                name,
+                show(a),
+                show(b)
+            ));
+        }
+    }
+
+    if preview.body != production.body {
+        differences.push(match (preview.text(), production.text()) {
+            (Some(a), Some(b)) => {
+                let (a, b): (Vec<&str>, Vec<&str>) = (a.lines().collect(), b.lines().collect());
+                fn line<'a>(lines: &[&'a str], i: usize) -> &'a str {
+                    lines.get(i).cloned().unwrap_or("")
+                }
+                // the lines can all be the same when only the line endings
+                // or the trailing newlines differ
+                match (0..a.len().max(b.len())).find(|&i| line(&a, i) != line(&b, i)) {
+                    Some(i) => format!(
+                        "body differs at line {}: preview {:?}, production {:?}",
+                        i + 1,
+                        line(&a, i),
+                        line(&b, i)
+                    ),
+                    None => format!(
+                        "body differs in line endings: preview {} bytes, production {} bytes",
+                        preview.body.len(),
+                        production.body.len()
+                    ),
+                }
+            }
+            _ => format!(
+                "body differs: preview {} bytes, production {} bytes",
+                preview.body.len(),
+                production.body.len()
+            ),
+        });
+    }
+
+    differences
+}
+
+// The headers of `response` by lowercase name, leaving out `ignored`.
+fn headers(response: &PreviewResponse, ignored: &[String]) -> BTreeMap<String, String> {
+    let mut headers: BTreeMap<String, String> = BTreeMap::new();
+    for (name, value) in &response.headers {
+        let name = name.to_lowercase();
+        if ignored.contains(&name) {
+            continue;
+        }
+        headers
+            .entry(name)
+            .and_modify(|v| {
+                v.push_str(", ");
+                v.push_str(value);
+            })
+            .or_insert_with(|| value.to_string());
+    }
+    headers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::time::Duration;
+
+    use reqwest::StatusCode;
+
+    fn response(status: u16, headers: &[(&str, &str)], body: &[u8]) -> PreviewResponse {
+        PreviewResponse {
+            status: StatusCode::from_u16(status).unwrap(),
+            headers: headers
+                .iter()
+                .map(|(n, v)| (n.to_string(), v.to_string()))
+                .collect(),
+            body: body.to_vec(),
+            elapsed: Duration::from_millis(1),
+        }
+    }
+
+    #[test]
+    fn it_ignores_edge_headers() {
+        let ignored = vec!["date".to_string(), "x-request-id".to_string()];
+        let a = response(200, &[("Date", "Mon"), ("X-Request-Id", "1")], b"hi");
+        let b = response(200, &[("date", "Tue"), ("x-request-id", "2")], b"hi");
+        assert!(differences(&a, &b, &ignored).is_empty());
+    }
+
+    #[test]
+    fn it_reports_differences() {
+        let a = response(200, &[("Content-Type", "text/plain")], b"one\ntwo\n");
+        let b = response(404, &[("X-Cache", "HIT")], b"one\nthree\n");
+        assert_eq!(
+            differences(&a, &b, &[]),
+            vec![
+                "status: preview HTTP 200 OK, production HTTP 404 Not Found",
+                "header content-type: preview \"text/plain\", production (none)",
+                "header x-cache: preview (none), production \"HIT\"",
+                "body differs at line 2: preview \"two\", production \"three\"",
+            ]
+        );
+
+        let b = response(200, &[("Content-Type", "text/plain")], b"\0\x01");
+        assert_eq!(
+            differences(&a, &b, &[]),
+            vec!["body differs: preview 8 bytes, production 2 bytes"]
+        );
+    }
+
+    #[test]
+    fn it_reports_differences_in_line_endings() {
+        let a = response(200, &[], b"a\n");
+        let b = response(200, &[], b"a");
+        assert_eq!(
+            differences(&a, &b, &[]),
+            vec!["body differs in line endings: preview 2 bytes, production 1 bytes"]
+        );
+
+        let b = response(200, &[], b"a\r\n\n");
+        assert_eq!(
+            differences(&a, &b, &[]),
+            vec!["body differs in line endings: preview 2 bytes, production 4 bytes"]
+        );
+    }
+}
@@ -0,0 +1,216 @@
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use serde::Deserialize;
+
+use super::{exchange, HTTPMethod, PreviewOptions, PreviewRequest, Target};
+use crate::terminal::message;
+
+// Headers that only concern the connection the request was recorded on, or
+// that HTTP/2 recordings carry as pseudo-headers, and are not replayed
+const SKIPPED_HEADERS: &[&str] = &[
+    "connection",
+    "content-length",
+    "host",
+    "keep-alive",
+    "transfer-encoding",
+];
+
+// Which recorded requests are replayed, and where the responses go.
+#[derive(Clone, Debug, Default)]
+pub struct ReplayOptions {
+    // The HAR file of the requests
+    pub har: String,
+    // A directory to save each response to, as JSON
+    pub save_responses: Option<String>,
+}
+
+// The parts of a HAR file that are replayed; see
+// http://www.softwareishard.com/blog/har-12-spec/
+#[derive(Debug, Deserialize)]
+struct Har {
+    log: Log,
+}
+
+#[derive(Debug, Deserialize)]
+struct Log {
+    entries: Vec<Entry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Entry {
+    request: HarRequest,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct HarRequest {
+    method: String,
+    url: String,
+    #[serde(default)]
+    headers: Vec<Header>,
+    post_data: Option<PostData>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Header {
+    name: String,
+    value: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct PostData {
+    #[serde(default)]
+    text: String,
+    // Some tools record binary bodies in base64
+    encoding: Option<String>,
+}
+
+// Sends each request recorded in the HAR file to `target`, printing the
+// responses like `preview` does, and saving them if asked to.
+pub fn replay(
+    client: &reqwest::Client,
+    target: &Target,
+    replay: &ReplayOptions,
+    options: &PreviewOptions,
+) -> Result<(), failure::Error> {
+    let requests = read(&replay.har)?;
+    if let Some(dir) = &replay.save_responses {
+        fs::create_dir_all(dir)?;
+    }
+    message::working(&format!(
+        "Replaying {} requests from {}",
+        requests.len(),
+        replay.har
+    ));
+
+    let mut failed = 0;
+    for (i, request) in requests.iter().enumerate() {
+        let result = exchange(client, request, target, options).and_then(|response| {
+            if let Some(dir) = &replay.save_responses {
+                let path = Path::new(dir).join(file_name(i, request));
+                fs::write(&path, response.to_json()?)?;
+                message::detail(&format!("Saved the response to {}", path.display()));
+            }
+            Ok(())
+        });
+        if let Err(e) = result {
+            message::user_error(&format!("{} {} failed: {}", request.method, request.url, e));
+            failed += 1;
+        }
+    }
+
+    if failed > 0 {
+        failure::bail!("{} of {} requests failed", failed, requests.len());
+    }
+    Ok(())
+}
+
+// The requests recorded in the HAR file at `path`, in order. Requests with a
+// method that cannot be previewed are skipped.
+fn read(path: &str) -> Result<Vec<PreviewRequest>, failure::Error> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) => failure::bail!("Could not read the HAR file {}: {}", path, e),
+    };
+    let har: Har = match serde_json::from_str(&contents) {
+        Ok(har) => har,
+        Err(e) => failure::bail!("Invalid HAR file {}: {}", path, e),
+    };
+
+    let mut requests = vec![];
+    for entry in har.log.entries {
+        let recorded = entry.request;
+        let method = match HTTPMethod::from_str(&recorded.method) {
+            Ok(method) => method,
+            Err(e) => {
+                message::warn(&format!("Skipping {}: {}", recorded.url, e));
+                continue;
+            }
+        };
+        let headers: Vec<String> = recorded
+            .headers
+            .iter()
+            .filter(|h| !h.name.starts_with(':'))
+            .filter(|h| !SKIPPED_HEADERS.contains(&h.name.to_lowercase().as_str()))
+            .map(|h| format!("{}: {}", h.name, h.value))
+            .collect();
+        let headers: Vec<&str> = headers.iter().map(|h| h.as_str()).collect();
+        let body = match recorded.post_data {
+            Some(PostData { text, encoding }) => {
+                if encoding.map(|e| e == "base64") == Some(true) {
+                    Some(base64::decode(&text)?)
+                } else {
+                    Some(text.into_bytes())
+                }
+            }
+            None => None,
+        };
+
+        requests.push(PreviewRequest::new(
+            method,
+            Some(&recorded.url),
+            false,
+            &headers,
+            body,
+        )?);
+    }
+
+    Ok(requests)
+}
+
+// The name of the file the response to the `i`th request is saved to
+fn file_name(i: usize, request: &PreviewRequest) -> String {
+    let path: String = request
+        .url
+        .path()
+        .trim_matches('/')
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .take(60)
+        .collect();
+    format!(
+        "{:03}-{}-{}.json",
+        i + 1,
+        request.method.to_string().to_lowercase(),
+        if path.is_empty() { "index" } else { &path }
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_reads_har_requests() {
+        let path = std::env::temp_dir().join(format!("wrangler-{}.har", std::process::id()));
+        fs::write(
+            &path,
+            r#"{ "log": { "version": "1.2", "entries": [
+                { "request": { "method": "GET", "url": "https://example.com/",
+                  "headers": [{ "name": ":authority", "value": "example.com" },
+                              { "name": "Host", "value": "example.com" },
+                              { "name": "Accept", "value": "text/html" }] } },
+                { "request": { "method": "CONNECT", "url": "https://example.com:443" } },
+                { "request": { "method": "POST", "url": "https://example.com/api/items?x=1",
+                  "headers": [], "postData": { "mimeType": "application/json", "text": "{\"a\":1}" } } }
+            ] } }"#,
+        )
+        .unwrap();
+
+        let requests = read(path.to_str().unwrap()).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(requests.len(), 2);
+        assert_eq!(
+            requests[0].headers,
+            vec![("Accept".to_string(), "text/html".to_string())]
+        );
+        assert_eq!(requests[0].body, None);
+        assert_eq!(requests[1].method, HTTPMethod::Post);
+        assert_eq!(requests[1].path_and_query(), "/api/items?x=1");
+        assert_eq!(requests[1].body, Some(b"{\"a\":1}".to_vec()));
+        assert_eq!(file_name(1, &requests[1]), "002-post-api_items.json");
+    }
+}
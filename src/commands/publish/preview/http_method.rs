@@ -1,8 +1,40 @@
+use std::fmt;
 use std::str::FromStr;
 
+use reqwest::Method;
+
+#[derive(Clone, Debug, PartialEq)]
 pub enum HTTPMethod {
     Get,
     Post,
+    Put,
+    Patch,
+    Delete,
+    Head,
+    Options,
+}
+
+impl HTTPMethod {
+    // Whether a request body is sent with this method; GET, HEAD and OPTIONS
+    // requests never carry one.
+    pub fn has_body(&self) -> bool {
+        match self {
+            HTTPMethod::Post | HTTPMethod::Put | HTTPMethod::Patch | HTTPMethod::Delete => true,
+            HTTPMethod::Get | HTTPMethod::Head | HTTPMethod::Options => false,
+        }
+    }
+
+    pub fn method(&self) -> Method {
+        match self {
+            HTTPMethod::Get => Method::GET,
+            HTTPMethod::Post => Method::POST,
+            HTTPMethod::Put => Method::PUT,
+            HTTPMethod::Patch => Method::PATCH,
+            HTTPMethod::Delete => Method::DELETE,
+            HTTPMethod::Head => Method::HEAD,
+            HTTPMethod::Options => Method::OPTIONS,
+        }
+    }
 }
 
 impl Default for HTTPMethod {
@@ -11,13 +43,48 @@ impl Default for HTTPMethod {
     }
 }
 
+impl fmt::Display for HTTPMethod {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.method())
+    }
+}
+
 impl FromStr for HTTPMethod {
     type Err = failure::Error;
     fn from_str(s: &str) -> Result<Self, failure::Error> {
-        match s {
+        match s.to_lowercase().as_str() {
             "get" => Ok(HTTPMethod::Get),
             "post" => Ok(HTTPMethod::Post),
-            _ => Ok(HTTPMethod::default()),
+            "put" => Ok(HTTPMethod::Put),
+            "patch" => Ok(HTTPMethod::Patch),
+            "delete" => Ok(HTTPMethod::Delete),
+            "head" => Ok(HTTPMethod::Head),
+            "options" => Ok(HTTPMethod::Options),
+            _ => failure::bail!(
+                "Unknown HTTP method `{}`. Use one of get, post, put, patch, delete, head or options.",
+                s
+            ),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_parses_methods_case_insensitively() {
+        assert_eq!(HTTPMethod::from_str("get").unwrap(), HTTPMethod::Get);
+        assert_eq!(HTTPMethod::from_str("DELETE").unwrap(), HTTPMethod::Delete);
+        assert_eq!(
+            HTTPMethod::from_str("Options").unwrap(),
+            HTTPMethod::Options
+        );
+    }
+
+    #[test]
+    fn it_rejects_unknown_methods() {
+        assert!(HTTPMethod::from_str("fetch").is_err());
+        assert!(HTTPMethod::from_str("").is_err());
+    }
+}
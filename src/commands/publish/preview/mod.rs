@@ -1,14 +1,28 @@
-use std::process::Command;
+use std::env;
+use std::process::{Command, Stdio};
 
+mod compare;
+mod har;
 mod http_method;
+mod request;
+mod response;
+mod target;
+pub use compare::CompareOptions;
+pub use har::{replay, ReplayOptions};
 pub use http_method::HTTPMethod;
+pub use request::{read_body, PreviewRequest};
+pub use response::PreviewResponse;
+pub use target::Target;
 
 use crate::commands::publish;
 
+use log::info;
 use serde::Deserialize;
 use uuid::Uuid;
 
 use crate::commands;
+use crate::commands::build::watch::{self, Watcher};
+use crate::commands::build::BuildOptions;
 use crate::http;
 use crate::settings::project::Project;
 use crate::terminal::message;
@@ -18,105 +32,314 @@ struct Preview {
     pub id: String,
 }
 
+// How the exchange with the previewed worker is reported.
+#[derive(Debug, Default)]
+pub struct PreviewOptions {
+    // Print the request that was sent as well as the response
+    pub verbose: bool,
+    // Print the response as JSON on stdout, and everything else on stderr
+    pub json: bool,
+    // Only print the preview URL, without opening a browser
+    pub headless: bool,
+    // Update the preview and replay the request whenever the sources change
+    pub watch: bool,
+    // Compare the responses of the preview with those of production, rather
+    // than printing them
+    pub compare: Option<CompareOptions>,
+    // Replay the requests of a HAR file rather than sending the request
+    pub replay: Option<ReplayOptions>,
+}
+
+const CREATE_ADDRESS: &str = "https://cloudflareworkers.com/script";
+
+// Where previews are served, for the preview picked by the cookie
+pub const PREVIEW_ADDRESS: &str = "https://00000000000000000000000000000000.cloudflareworkers.com";
+
 pub fn preview(
     project: &Project,
-    method: Result<HTTPMethod, failure::Error>,
-    body: Option<String>,
+    request: PreviewRequest,
+    options: &PreviewOptions,
+    build: &BuildOptions,
 ) -> Result<(), failure::Error> {
-    let create_address = "https://cloudflareworkers.com/script";
+    if options.json {
+        message::to_stderr(true);
+    }
 
     let client = http::client();
 
-    commands::build(&project)?;
+    let script_id = upload(&client, project, None, build)?;
+
+    // the session is kept across uploads, so that an open preview keeps working
+    let session = Uuid::new_v4().to_simple().to_string();
+
+    let target = Target::Preview {
+        script_id: script_id.clone(),
+        session: session.clone(),
+    };
+    if let Some(compare) = &options.compare {
+        return compare::compare(&client, &request, &target, compare, options.json);
+    }
+    if let Some(replay) = &options.replay {
+        return har::replay(&client, &target, replay, options);
+    }
+
+    let response = exchange(&client, &request, &target, options)?;
+    open(&request, &script_id, options)?;
+
+    if options.watch {
+        return watch(
+            &client, project, &request, script_id, &session, options, build,
+        );
+    }
+
+    if response.status.is_server_error() {
+        failure::bail!("Your worker responded with {}", response.status_line());
+    }
+
+    Ok(())
+}
+
+// Rebuilds, re-uploads and replays the request whenever the sources change,
+// until interrupted. A failed iteration is reported and waits for the next
+// change. The browser is only opened once, and told about a new preview.
+fn watch(
+    client: &reqwest::Client,
+    project: &Project,
+    request: &PreviewRequest,
+    mut script_id: String,
+    session: &str,
+    options: &PreviewOptions,
+    build: &BuildOptions,
+) -> Result<(), failure::Error> {
+    let watcher = Watcher::new(project)?;
+    loop {
+        message::info("Watching for changes...");
+        let changed = watcher.wait()?;
+        message::working(&format!(
+            "{} changed, updating the preview",
+            watch::describe(&changed)
+        ));
+
+        let result = upload(client, project, Some(&script_id), build).and_then(|id| {
+            if id != script_id {
+                script_id = id;
+                message::preview(&format!(
+                    "Your preview is now at {}",
+                    browser_preview(request, &script_id)
+                ));
+            }
+            let target = Target::Preview {
+                script_id: script_id.clone(),
+                session: session.to_string(),
+            };
+            exchange(client, request, &target, options)
+        });
+        if let Err(e) = result {
+            message::user_error(&e.to_string());
+        }
+    }
+}
+
+// Builds the project and uploads it as a preview, or updates the preview
+// `script_id` if there is one. Returns the ID of the uploaded preview.
+pub fn upload(
+    client: &reqwest::Client,
+    project: &Project,
+    script_id: Option<&str>,
+    build: &BuildOptions,
+) -> Result<String, failure::Error> {
+    commands::build(&project, build)?;
 
     let script_upload_form = publish::build_script_upload_form(project)?;
+    publish::validate_wasm(&script_upload_form)?;
 
+    let address = match script_id {
+        Some(id) => format!("{}/{}", CREATE_ADDRESS, id),
+        None => CREATE_ADDRESS.to_string(),
+    };
     let res = client
-        .post(create_address)
-        .multipart(script_upload_form)
+        .post(&address)
+        .multipart(script_upload_form.into_form()?)
         .send();
 
     let p: Preview = serde_json::from_str(&res?.text()?)?;
+    Ok(p.id)
+}
 
-    let session = Uuid::new_v4().to_simple();
+// The cookie that picks the preview `script_id` and tells it the URL the
+// worker sees is on `host`, over HTTPS or not.
+pub fn cookie(script_id: &str, session: &str, https: bool, host: &str) -> String {
+    format!(
+        "__ew_fiddle_preview={}{}{}{}",
+        script_id,
+        session,
+        if https { 1 } else { 0 },
+        host
+    )
+}
 
-    let preview_host = "example.com";
-    let https = 1;
-    let script_id = &p.id;
+// Sends the request to the preview and prints the response.
+fn exchange(
+    client: &reqwest::Client,
+    request: &PreviewRequest,
+    target: &Target,
+    options: &PreviewOptions,
+) -> Result<PreviewResponse, failure::Error> {
+    let response = send(client, request, target, options)?;
+    if options.json {
+        println!("{}", response.to_json()?);
+    } else {
+        message::preview(&format!(
+            "Your worker responded with {} in {} ms",
+            response.status_line(),
+            response.elapsed.as_millis()
+        ));
+        message::detail(&response.render()?);
+    }
 
-    let preview_address = "https://00000000000000000000000000000000.cloudflareworkers.com";
-    let cookie = format!(
-        "__ew_fiddle_preview={}{}{}{}",
-        script_id, session, https, preview_host
-    );
+    Ok(response)
+}
 
-    let method = method.unwrap_or_default();
+fn open(
+    request: &PreviewRequest,
+    script_id: &str,
+    options: &PreviewOptions,
+) -> Result<(), failure::Error> {
+    let browser_preview = browser_preview(request, script_id);
+    message::preview(&format!("Your preview is at {}", browser_preview));
 
-    let worker_res = match method {
-        HTTPMethod::Get => get(preview_address, cookie, client)?,
-        HTTPMethod::Post => post(preview_address, cookie, client, body)?,
-    };
-    let msg = format!("Your worker responded with: {}", worker_res);
-    message::preview(&msg);
+    let browser = env::var("BROWSER").ok().filter(|b| !b.trim().is_empty());
+    if options.headless {
+        return Ok(());
+    } else if !atty::is(atty::Stream::Stdout) {
+        info!("not opening a browser, stdout is not a terminal");
+        return Ok(());
+    } else if browser.is_none() && !has_display() {
+        info!("not opening a browser, there is no display");
+        return Ok(());
+    }
 
-    open(preview_host, https, script_id)?;
+    let mut command = match browser {
+        Some(browser) => {
+            let args = browser_args(&browser, &browser_preview);
+            let mut c = Command::new(&args[0]);
+            c.args(&args[1..]);
+            c
+        }
+        None if cfg!(target_os = "windows") => {
+            let mut c = Command::new("cmd");
+            c.arg("/C").arg("start").arg("").arg(&browser_preview);
+            c
+        }
+        None if cfg!(target_os = "linux") => {
+            let mut c = Command::new("xdg-open");
+            c.arg(&browser_preview);
+            c
+        }
+        None => {
+            let mut c = Command::new("open");
+            c.arg(&browser_preview);
+            c
+        }
+    };
+    // don't wait on the browser, which may not return until it is closed
+    if let Err(e) = command.stdout(Stdio::null()).stderr(Stdio::null()).spawn() {
+        message::warn(&format!("Could not open a browser: {}", e));
+    }
 
     Ok(())
 }
 
-fn open(preview_host: &str, https: u8, script_id: &str) -> Result<(), failure::Error> {
-    let https_str = match https {
-        1 => "https://",
-        0 => "http://",
-        // hrm.
-        _ => "",
+// The page of cloudflareworkers.com that shows the preview `script_id`
+// receiving `request`.
+fn browser_preview(request: &PreviewRequest, script_id: &str) -> String {
+    let https_str = if request.https() {
+        "https://"
+    } else {
+        "http://"
     };
 
-    let browser_preview = format!(
-        "https://cloudflareworkers.com/#{}:{}{}",
-        script_id, https_str, preview_host
-    );
-    let windows_cmd = format!("start {}", browser_preview);
-    let mac_cmd = format!("open {}", browser_preview);
-    let linux_cmd = format!("xdg-open {}", browser_preview);
-
-    let _output = if cfg!(target_os = "windows") {
-        Command::new("cmd").args(&["/C", &windows_cmd]).output()?
-    } else if cfg!(target_os = "linux") {
-        Command::new("sh").arg("-c").arg(&linux_cmd).output()?
+    format!(
+        "https://cloudflareworkers.com/#{}:{}{}{}",
+        script_id,
+        https_str,
+        request.host(),
+        request.path_and_query()
+    )
+}
+
+// The program and arguments to open `url` with, from a `BROWSER` such as
+// `firefox --new-window`. A `%s` in it is replaced with the URL, which is
+// added at the end otherwise.
+fn browser_args(browser: &str, url: &str) -> Vec<String> {
+    let mut args: Vec<String> = browser.split_whitespace().map(str::to_string).collect();
+    if args.iter().any(|a| a.contains("%s")) {
+        for arg in args.iter_mut() {
+            *arg = arg.replace("%s", url);
+        }
     } else {
-        Command::new("sh").arg("-c").arg(&mac_cmd).output()?
-    };
+        args.push(url.to_string());
+    }
+    args
+}
 
-    Ok(())
+// Whether there is a graphical session to open a browser in. Only X11 and
+// Wayland sessions can be detected, so other platforms always have one.
+fn has_display() -> bool {
+    if !cfg!(target_os = "linux") {
+        return true;
+    }
+    ["DISPLAY", "WAYLAND_DISPLAY"]
+        .iter()
+        .any(|var| env::var_os(var).map(|v| !v.is_empty()) == Some(true))
 }
 
-fn get(
-    preview_address: &str,
-    cookie: String,
-    client: reqwest::Client,
-) -> Result<String, failure::Error> {
-    let res = client.get(preview_address).header("Cookie", cookie).send();
-    let msg = format!("GET {}", preview_address);
+fn send(
+    client: &reqwest::Client,
+    request: &PreviewRequest,
+    target: &Target,
+    options: &PreviewOptions,
+) -> Result<PreviewResponse, failure::Error> {
+    let method = &request.method;
+    let msg = format!("{} {}", method, request.url);
     message::preview(&msg);
-    Ok(res?.text()?)
+    if request.body.is_some() && !method.has_body() {
+        message::warn(&format!("{} requests have no body, ignoring it", method));
+    }
+
+    let req = target.build(client, request)?;
+    if options.verbose {
+        message::detail(&format!("> {} {}", req.method(), req.url()));
+        for (name, value) in req.headers() {
+            message::detail(&format!(
+                "> {}: {}",
+                name,
+                String::from_utf8_lossy(value.as_bytes())
+            ));
+        }
+        if let (Some(body), true) = (&request.body, method.has_body()) {
+            message::detail(&format!("> ({} bytes of body)", body.len()));
+        }
+    }
+
+    target::execute(client, req)
 }
 
-fn post(
-    preview_address: &str,
-    cookie: String,
-    client: reqwest::Client,
-    body: Option<String>,
-) -> Result<String, failure::Error> {
-    let res = match body {
-        Some(s) => client
-            .post(preview_address)
-            .header("Cookie", cookie)
-            .body(s)
-            .send(),
-        None => client.post(preview_address).header("Cookie", cookie).send(),
-    };
-    let msg = format!("POST {}", preview_address);
-    message::preview(&msg);
-    Ok(res?.text()?)
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_splits_the_browser_command() {
+        let url = "https://example.com/#a";
+        assert_eq!(browser_args("firefox", url), vec!["firefox", url]);
+        assert_eq!(
+            browser_args("open -a Safari", url),
+            vec!["open", "-a", "Safari", url]
+        );
+        assert_eq!(
+            browser_args("chromium --app=%s", url),
+            vec!["chromium", "--app=https://example.com/#a"]
+        );
+    }
 }
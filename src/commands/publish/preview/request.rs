@@ -0,0 +1,198 @@
+use std::fs;
+use std::io::{self, Read};
+
+use reqwest::Url;
+
+use super::HTTPMethod;
+
+// The host the preview service pretends the worker is served on, unless a
+// `--url` says otherwise.
+const DEFAULT_URL: &str = "https://example.com/";
+
+// The request to send to the previewed worker, as the worker will see it.
+#[derive(Debug)]
+pub struct PreviewRequest {
+    pub method: HTTPMethod,
+    pub url: Url,
+    pub headers: Vec<(String, String)>,
+    pub body: Option<Vec<u8>>,
+}
+
+impl PreviewRequest {
+    // `url` may be a full URL or only a path and query, which is then
+    // resolved against https://example.com. `http` forces the request to be
+    // made over plain HTTP.
+    pub fn new(
+        method: HTTPMethod,
+        url: Option<&str>,
+        http: bool,
+        headers: &[&str],
+        body: Option<Vec<u8>>,
+    ) -> Result<PreviewRequest, failure::Error> {
+        let mut url = parse_url(url.unwrap_or("/"))?;
+        if http && url.set_scheme("http").is_err() {
+            failure::bail!("Could not preview {} over HTTP", url);
+        }
+
+        let headers = headers
+            .iter()
+            .map(|h| parse_header(h))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(PreviewRequest {
+            method,
+            url,
+            headers,
+            body,
+        })
+    }
+
+    // The same request, made for another URL. A path and query is resolved
+    // against the URL of this request.
+    pub fn at(&self, url: &str) -> Result<PreviewRequest, failure::Error> {
+        let url = if url.starts_with('/') {
+            self.url.join(url)?
+        } else {
+            parse_url(url)?
+        };
+
+        Ok(PreviewRequest {
+            method: self.method.clone(),
+            url,
+            headers: self.headers.clone(),
+            body: self.body.clone(),
+        })
+    }
+
+    pub fn https(&self) -> bool {
+        self.url.scheme() == "https"
+    }
+
+    // The host, with its port if there is one, as sent in the preview cookie.
+    pub fn host(&self) -> String {
+        let host = self.url.host_str().unwrap_or_default();
+        match self.url.port() {
+            Some(port) => format!("{}:{}", host, port),
+            None => host.to_string(),
+        }
+    }
+
+    pub fn path_and_query(&self) -> String {
+        match self.url.query() {
+            Some(query) => format!("{}?{}", self.url.path(), query),
+            None => self.url.path().to_string(),
+        }
+    }
+}
+
+fn parse_url(url: &str) -> Result<Url, failure::Error> {
+    let base = Url::parse(DEFAULT_URL)?;
+    let parsed = if url.contains("://") {
+        Url::parse(url)
+    } else if url.starts_with('/') {
+        base.join(url)
+    } else {
+        // a bare host, possibly followed by a path
+        Url::parse(&format!("https://{}", url))
+    };
+
+    match parsed {
+        Ok(u) if u.scheme() == "http" || u.scheme() == "https" => Ok(u),
+        Ok(u) => failure::bail!("Can only preview http and https URLs, not {}", u),
+        Err(e) => failure::bail!("Invalid preview URL `{}`: {}", url, e),
+    }
+}
+
+fn parse_header(header: &str) -> Result<(String, String), failure::Error> {
+    let mut split = header.splitn(2, ':');
+    match (split.next(), split.next()) {
+        (Some(name), Some(value)) if !name.trim().is_empty() => {
+            Ok((name.trim().to_string(), value.trim().to_string()))
+        }
+        _ => failure::bail!(
+            "Invalid header `{}`; headers are passed as `Name: value`",
+            header
+        ),
+    }
+}
+
+// Reads the request body from the `--body` string or the `--body-file` path,
+// either of which reads stdin when it is `-`. A file is read as is, so it
+// may contain binary data.
+pub fn read_body(
+    body: Option<&str>,
+    body_file: Option<&str>,
+) -> Result<Option<Vec<u8>>, failure::Error> {
+    match (body, body_file) {
+        (Some("-"), _) | (_, Some("-")) => {
+            let mut bytes = vec![];
+            io::stdin().read_to_end(&mut bytes)?;
+            Ok(Some(bytes))
+        }
+        (_, Some(path)) => match fs::read(path) {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(e) => failure::bail!("Could not read the request body from {}: {}", path, e),
+        },
+        (Some(body), None) => Ok(Some(body.as_bytes().to_vec())),
+        (None, None) => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(url: Option<&str>, http: bool) -> PreviewRequest {
+        PreviewRequest::new(HTTPMethod::Get, url, http, &[], None).unwrap()
+    }
+
+    #[test]
+    fn it_defaults_to_example_com() {
+        let req = request(None, false);
+        assert!(req.https());
+        assert_eq!(req.host(), "example.com");
+        assert_eq!(req.path_and_query(), "/");
+    }
+
+    #[test]
+    fn it_resolves_paths_and_hosts() {
+        let req = request(Some("/api/items?page=2"), false);
+        assert_eq!(req.host(), "example.com");
+        assert_eq!(req.path_and_query(), "/api/items?page=2");
+
+        let req = request(Some("http://my.site.dev:8080/a"), false);
+        assert!(!req.https());
+        assert_eq!(req.host(), "my.site.dev:8080");
+        assert_eq!(req.path_and_query(), "/a");
+
+        let req = request(Some("my.site.dev/b"), true);
+        assert!(!req.https());
+        assert_eq!(req.host(), "my.site.dev");
+
+        let req = req.at("/c?d=e").unwrap();
+        assert!(!req.https());
+        assert_eq!(req.host(), "my.site.dev");
+        assert_eq!(req.path_and_query(), "/c?d=e");
+    }
+
+    #[test]
+    fn it_parses_headers() {
+        let req = PreviewRequest::new(
+            HTTPMethod::Get,
+            None,
+            false,
+            &["Content-Type: application/json", "X-Empty:"],
+            None,
+        )
+        .unwrap();
+        assert_eq!(
+            req.headers,
+            vec![
+                ("Content-Type".to_string(), "application/json".to_string()),
+                ("X-Empty".to_string(), "".to_string()),
+            ]
+        );
+
+        assert!(PreviewRequest::new(HTTPMethod::Get, None, false, &["nope"], None).is_err());
+    }
+}
@@ -0,0 +1,161 @@
+use std::fs;
+use std::path::Path;
+use std::str;
+use std::time::{Duration, Instant};
+
+use reqwest::{Response, StatusCode};
+use serde::Serialize;
+
+// Where a binary response body is saved, rather than printed
+const BINARY_BODY_PATH: &str = "./worker/preview_response.bin";
+
+// The response of the previewed worker, read in full.
+#[derive(Debug)]
+pub struct PreviewResponse {
+    pub status: StatusCode,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+    // From sending the request to having read the whole body
+    pub elapsed: Duration,
+}
+
+#[derive(Serialize)]
+struct JsonResponse<'a> {
+    status: u16,
+    headers: Vec<JsonHeader<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    body: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    body_base64: Option<String>,
+    time_ms: u64,
+}
+
+#[derive(Serialize)]
+struct JsonHeader<'a> {
+    name: &'a str,
+    value: &'a str,
+}
+
+impl PreviewResponse {
+    // Reads the whole response to a request sent at `start`.
+    pub fn read(mut res: Response, start: Instant) -> Result<PreviewResponse, failure::Error> {
+        let headers = res
+            .headers()
+            .iter()
+            .map(|(name, value)| {
+                (
+                    name.as_str().to_string(),
+                    String::from_utf8_lossy(value.as_bytes()).to_string(),
+                )
+            })
+            .collect();
+        let mut body = vec![];
+        res.copy_to(&mut body)?;
+
+        Ok(PreviewResponse {
+            status: res.status(),
+            headers,
+            body,
+            elapsed: start.elapsed(),
+        })
+    }
+
+    // The body as text, unless it is binary.
+    pub fn text(&self) -> Option<&str> {
+        match str::from_utf8(&self.body) {
+            Ok(text) if !text.contains('\0') => Some(text),
+            _ => None,
+        }
+    }
+
+    pub fn status_line(&self) -> String {
+        format!(
+            "HTTP {} {}",
+            self.status.as_u16(),
+            self.status.canonical_reason().unwrap_or_default()
+        )
+    }
+
+    // The headers and body for the terminal. A binary body is saved to a
+    // file instead, and only mentioned.
+    pub fn render(&self) -> Result<String, failure::Error> {
+        let mut lines = vec![];
+        for (name, value) in &self.headers {
+            lines.push(format!("{}: {}", name, value));
+        }
+        lines.push(String::new());
+
+        match self.text() {
+            Some(text) => lines.push(text.to_string()),
+            None => {
+                if let Some(dir) = Path::new(BINARY_BODY_PATH).parent() {
+                    fs::create_dir_all(dir)?;
+                }
+                fs::write(BINARY_BODY_PATH, &self.body)?;
+                lines.push(format!(
+                    "Binary body of {} bytes saved to {}",
+                    self.body.len(),
+                    BINARY_BODY_PATH
+                ));
+            }
+        }
+
+        Ok(lines.join("\n"))
+    }
+
+    pub fn to_json(&self) -> Result<String, failure::Error> {
+        let text = self.text();
+        let json = JsonResponse {
+            status: self.status.as_u16(),
+            headers: self
+                .headers
+                .iter()
+                .map(|(name, value)| JsonHeader { name, value })
+                .collect(),
+            body: text,
+            body_base64: match text {
+                Some(_) => None,
+                None => Some(base64::encode(&self.body)),
+            },
+            time_ms: self.elapsed.as_millis() as u64,
+        };
+
+        Ok(serde_json::to_string_pretty(&json)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(body: &[u8]) -> PreviewResponse {
+        PreviewResponse {
+            status: StatusCode::OK,
+            headers: vec![("content-type".to_string(), "text/plain".to_string())],
+            body: body.to_vec(),
+            elapsed: Duration::from_millis(42),
+        }
+    }
+
+    #[test]
+    fn it_renders_text_responses() {
+        let rendered = response(b"hello").render().unwrap();
+        assert_eq!(rendered, "content-type: text/plain\n\nhello");
+    }
+
+    #[test]
+    fn it_encodes_binary_bodies_in_json() {
+        let json: serde_json::Value =
+            serde_json::from_str(&response(&[0, 159, 146, 150]).to_json().unwrap()).unwrap();
+        assert_eq!(json["status"], 200);
+        assert_eq!(json["headers"][0]["name"], "content-type");
+        assert_eq!(json["body_base64"], "AJ+Slg==");
+        assert!(json.get("body").is_none());
+        assert_eq!(json["time_ms"], 42);
+
+        let json: serde_json::Value =
+            serde_json::from_str(&response(b"hello").to_json().unwrap()).unwrap();
+        assert_eq!(json["body"], "hello");
+        assert!(json.get("body_base64").is_none());
+    }
+}
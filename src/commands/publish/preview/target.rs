@@ -0,0 +1,115 @@
+use std::time::Instant;
+
+use reqwest::{Client, Request, Url};
+use uuid::Uuid;
+
+use super::{cookie, PreviewRequest, PreviewResponse, PREVIEW_ADDRESS};
+
+// Where requests built like preview requests are sent: an uploaded preview,
+// or a worker deployed at a base URL.
+#[derive(Debug)]
+pub enum Target {
+    Preview { script_id: String, session: String },
+    Url(Url),
+}
+
+impl Target {
+    // The preview `script_id`, in a new session.
+    pub fn preview(script_id: String) -> Target {
+        Target::Preview {
+            script_id,
+            session: Uuid::new_v4().to_simple().to_string(),
+        }
+    }
+
+    pub fn url(base: &str) -> Result<Target, failure::Error> {
+        match Url::parse(base) {
+            Ok(url) => Ok(Target::Url(url)),
+            Err(e) => failure::bail!("Invalid URL `{}`: {}", base, e),
+        }
+    }
+
+    // Builds `request` for this target. A preview is asked for the URL of the
+    // request through its cookie; a deployed worker is asked for the path and
+    // query of the request under its base URL.
+    pub fn build(
+        &self,
+        client: &Client,
+        request: &PreviewRequest,
+    ) -> Result<Request, failure::Error> {
+        let method = &request.method;
+        let mut req = match self {
+            Target::Preview { script_id, session } => client
+                .request(
+                    method.method(),
+                    &format!("{}{}", PREVIEW_ADDRESS, request.path_and_query()),
+                )
+                .header(
+                    "Cookie",
+                    cookie(script_id, session, request.https(), &request.host()),
+                ),
+            Target::Url(base) => {
+                client.request(method.method(), url_for(base, &request.path_and_query()))
+            }
+        };
+        for (name, value) in &request.headers {
+            req = req.header(name.as_str(), value.as_str());
+        }
+        if let Some(body) = &request.body {
+            if method.has_body() {
+                req = req.body(body.clone());
+            }
+        }
+
+        Ok(req.build()?)
+    }
+
+    // Sends `request` and reads the whole response, timing both.
+    pub fn send(
+        &self,
+        client: &Client,
+        request: &PreviewRequest,
+    ) -> Result<PreviewResponse, failure::Error> {
+        let req = self.build(client, request)?;
+        execute(client, req)
+    }
+}
+
+// The URL of `path_and_query` under `base`, keeping the path of `base` so
+// that a worker routed under a prefix is sent its own requests.
+fn url_for(base: &Url, path_and_query: &str) -> Url {
+    let (path, query) = match path_and_query.find('?') {
+        Some(i) => (&path_and_query[..i], Some(&path_and_query[i + 1..])),
+        None => (path_and_query, None),
+    };
+    let mut url = base.clone();
+    url.set_path(&format!("{}{}", base.path().trim_end_matches('/'), path));
+    url.set_query(query);
+    url
+}
+
+pub fn execute(client: &Client, req: Request) -> Result<PreviewResponse, failure::Error> {
+    let start = Instant::now();
+    let res = client.execute(req)?;
+    PreviewResponse::read(res, start)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_keeps_the_base_path() {
+        let base = Url::parse("https://example.com/api/").unwrap();
+        assert_eq!(
+            url_for(&base, "/foo?x=1").as_str(),
+            "https://example.com/api/foo?x=1"
+        );
+
+        let base = Url::parse("https://example.com/api").unwrap();
+        assert_eq!(url_for(&base, "/").as_str(), "https://example.com/api/");
+
+        let base = Url::parse("https://example.com").unwrap();
+        assert_eq!(url_for(&base, "/foo").as_str(), "https://example.com/foo");
+    }
+}
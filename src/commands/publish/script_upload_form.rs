@@ -1,20 +1,124 @@
 use log::info;
 
 use reqwest::multipart::{Form, Part};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
 use std::fs;
 use std::path::Path;
 
 use crate::commands::build::wranglerjs::Bundle;
 use crate::settings::project::{Project, ProjectType};
 
-use super::{krate, Package};
+use super::Package;
 
-pub fn build_script_upload_form(project: &Project) -> Result<Form, failure::Error> {
+// A single part of the multipart form uploaded to the Workers API. Parts are
+// read into memory up-front so that they can be hashed before being sent.
+#[derive(Debug)]
+pub struct UploadPart {
+    pub name: String,
+    pub file_name: String,
+    pub mime: String,
+    pub contents: Vec<u8>,
+}
+
+impl UploadPart {
+    fn from_file(name: &str, path: &str) -> Result<UploadPart, failure::Error> {
+        let contents = fs::read(path)?;
+        let file_name = Path::new(path)
+            .file_name()
+            .and_then(|f| f.to_str())
+            .unwrap_or(name)
+            .to_string();
+
+        Ok(UploadPart {
+            name: name.to_string(),
+            mime: mime_for(&file_name).to_string(),
+            file_name,
+            contents,
+        })
+    }
+
+    fn from_text(name: &str, file_name: &str, contents: &str) -> UploadPart {
+        UploadPart {
+            name: name.to_string(),
+            file_name: file_name.to_string(),
+            mime: mime_for(file_name).to_string(),
+            contents: contents.as_bytes().to_vec(),
+        }
+    }
+
+    pub fn hash(&self) -> String {
+        format!("{:x}", Sha256::digest(&self.contents))
+    }
+}
+
+fn mime_for(file_name: &str) -> &'static str {
+    match Path::new(file_name).extension().and_then(|e| e.to_str()) {
+        Some("js") => "application/javascript",
+        Some("json") => "application/json",
+        Some("wasm") => "application/wasm",
+        _ => "application/octet-stream",
+    }
+}
+
+// The script, its metadata and any Wasm module, as they will be uploaded.
+#[derive(Debug)]
+pub struct ScriptUploadForm {
+    pub parts: Vec<UploadPart>,
+}
+
+#[derive(Debug, Deserialize)]
+struct UploadMetadata {
+    #[serde(default)]
+    bindings: Vec<UploadBinding>,
+}
+
+// A binding as declared in the metadata part.
+#[derive(Debug, Deserialize)]
+pub struct UploadBinding {
+    #[serde(rename = "type")]
+    pub binding_type: String,
+    pub name: String,
+    #[serde(default)]
+    pub part: String,
+}
+
+impl ScriptUploadForm {
+    // The bindings declared by the metadata part, if there is one.
+    pub fn bindings(&self) -> Result<Vec<UploadBinding>, failure::Error> {
+        match self.parts.iter().find(|p| p.name == "metadata") {
+            Some(part) => Ok(serde_json::from_slice::<UploadMetadata>(&part.contents)?.bindings),
+            None => Ok(vec![]),
+        }
+    }
+
+    pub fn script(&self) -> String {
+        self.parts
+            .iter()
+            .find(|p| p.name == "script")
+            .map(|p| String::from_utf8_lossy(&p.contents).to_string())
+            .unwrap_or_default()
+    }
+
+    pub fn into_form(self) -> Result<Form, failure::Error> {
+        let mut form = Form::new();
+        for part in self.parts {
+            let p = Part::bytes(part.contents)
+                .file_name(part.file_name)
+                .mime_str(&part.mime)?;
+            form = form.part(part.name, p);
+        }
+
+        Ok(form)
+    }
+}
+
+pub fn build_script_upload_form(project: &Project) -> Result<ScriptUploadForm, failure::Error> {
     let project_type = &project.project_type;
-    let script_upload_form = match project_type {
+    let parts = match project_type {
         ProjectType::Rust => {
             info!("Rust project detected. Publishing...");
-            build_multipart_script()?
+            build_bundle_form(project)?
         }
         ProjectType::JavaScript => {
             info!("JavaScript project detected. Publishing...");
@@ -22,91 +126,53 @@ pub fn build_script_upload_form(project: &Project) -> Result<Form, failure::Erro
         }
         ProjectType::Webpack => {
             info!("Webpack project detected. Publishing...");
-            build_webpack_form()?
+            build_bundle_form(project)?
         }
     };
 
-    Ok(script_upload_form)
+    Ok(ScriptUploadForm { parts })
 }
 
-fn build_js_script() -> Result<Form, failure::Error> {
+fn build_js_script() -> Result<Vec<UploadPart>, failure::Error> {
     let package = Package::new("./")?;
     let script_path = package.main()?;
     let metadata_json = r#"{"body_part":"script","bindings":[]}"#;
 
-    let metadata = Part::text(metadata_json)
-        .file_name("metadata.json")
-        .mime_str("application/json")?;
+    let script = UploadPart::from_file("script", &script_path)
+        .unwrap_or_else(|_| panic!("{} not found. Did you rename your js files?", &script_path));
+    let metadata = UploadPart::from_text("metadata", "metadata.json", metadata_json);
 
-    Ok(Form::new()
-        .file("script", &script_path)
-        .unwrap_or_else(|_| panic!("{} not found. Did you rename your js files?", &script_path))
-        .part("metadata", metadata))
+    Ok(vec![script, metadata])
 }
 
-fn build_multipart_script() -> Result<Form, failure::Error> {
-    let name = krate::Krate::new("./")?.name.replace("-", "_");
-    build_generated_dir()?;
-    concat_js(&name)?;
-
-    let metadata_path = "./worker/metadata_wasm.json";
-    let wasm_path = &format!("./pkg/{}_bg.wasm", name);
-    let script_path = "./worker/generated/script.js";
-
-    Ok(Form::new()
-        .file("metadata", metadata_path)
-        .unwrap_or_else(|_| panic!("{} not found. Did you delete it?", metadata_path))
-        .file("wasmprogram", wasm_path)
-        .unwrap_or_else(|_| panic!("{} not found. Have you run wrangler build?", wasm_path))
-        .file("script", script_path)
-        .unwrap_or_else(|_| panic!("{} not found. Did you rename your js files?", script_path)))
-}
+// The script, Wasm and metadata that the build of a Rust or webpack project
+// wrote as its bundle.
+fn build_bundle_form(project: &Project) -> Result<Vec<UploadPart>, failure::Error> {
+    let bundle = Bundle::for_project(project);
 
-fn build_generated_dir() -> Result<(), failure::Error> {
-    let dir = "./worker/generated";
-    if !Path::new(dir).is_dir() {
-        fs::create_dir("./worker/generated")?;
-    }
-
-    Ok(())
-}
-
-fn concat_js(name: &str) -> Result<(), failure::Error> {
-    let bindgen_js_path = format!("./pkg/{}.js", name);
-    let bindgen_js: String = fs::read_to_string(bindgen_js_path)?.parse()?;
-
-    let worker_js: String = fs::read_to_string("./worker/worker.js")?.parse()?;
-    let js = format!("{} {}", bindgen_js, worker_js);
-
-    fs::write("./worker/generated/script.js", js.as_bytes())?;
-    Ok(())
-}
-
-fn build_webpack_form() -> Result<Form, failure::Error> {
-    // FIXME(sven): shouldn't new
-    let bundle = Bundle::new();
-
-    let form = Form::new()
-        .file("metadata", bundle.metadata_path())
-        .unwrap_or_else(|_| panic!("{} not found. Did you delete it?", bundle.metadata_path()))
-        .file("script", bundle.script_path())
-        .unwrap_or_else(|_| {
+    let mut parts = vec![
+        UploadPart::from_file("metadata", &bundle.metadata_path())
+            .unwrap_or_else(|_| panic!("{} not found. Did you delete it?", bundle.metadata_path())),
+        UploadPart::from_file("script", &bundle.script_path()).unwrap_or_else(|_| {
             panic!(
                 "{} not found. Did you rename your js files?",
                 bundle.script_path()
             )
-        });
+        }),
+    ];
 
     if bundle.has_wasm() {
-        Ok(form
-            .file(bundle.get_wasm_binding(), bundle.wasm_path())
-            .unwrap_or_else(|_| {
-                panic!(
-                    "{} not found. Have you run wrangler build?",
-                    bundle.wasm_path()
-                )
-            }))
-    } else {
-        Ok(form)
+        parts.push(
+            UploadPart::from_file(&bundle.get_wasm_binding(), &bundle.wasm_path()).unwrap_or_else(
+                |_| {
+                    panic!(
+                        "{} not found. Have you run wrangler build?",
+                        bundle.wasm_path()
+                    )
+                },
+            ),
+        );
     }
+
+    Ok(parts)
 }
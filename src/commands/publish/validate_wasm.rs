@@ -0,0 +1,145 @@
+use log::info;
+
+use crate::commands::publish::ScriptUploadForm;
+use crate::terminal::message;
+use crate::wasm::WasmModule;
+
+// Checks the Wasm modules about to be uploaded, so that a broken module is
+// reported here rather than as an API error or at runtime. Malformed modules
+// and missing parts are errors; anything the Workers runtime may not be able
+// to satisfy is a warning.
+pub fn validate_wasm(form: &ScriptUploadForm) -> Result<(), failure::Error> {
+    let script = form.script();
+    for binding in form
+        .bindings()?
+        .iter()
+        .filter(|b| b.binding_type == "wasm_module")
+    {
+        let part = match form.parts.iter().find(|p| p.name == binding.part) {
+            Some(part) => part,
+            None => failure::bail!(
+                "The Wasm binding `{}` refers to the part `{}`, which is not part of the upload.",
+                binding.name,
+                binding.part
+            ),
+        };
+
+        let module = match WasmModule::parse_valid(&part.contents) {
+            Ok(module) => module,
+            Err(e) => failure::bail!(
+                "{} is not a valid Wasm module, and would be rejected by `WebAssembly.validate`: {}",
+                part.file_name,
+                e
+            ),
+        };
+
+        info!(
+            "Wasm module `{}` has {} imports and {} exports",
+            binding.name,
+            module.imports.len(),
+            module.exports.len()
+        );
+        for import in &module.imports {
+            info!(
+                "  import {} {}.{}",
+                import.kind, import.module, import.field
+            );
+        }
+        for export in &module.exports {
+            info!("  export {} {}", export.kind, export.field);
+        }
+
+        for warning in runtime_warnings(&module) {
+            message::warn(&warning);
+        }
+
+        if !references_identifier(&script, &binding.name) {
+            message::warn(&format!(
+                "Your script never references `{}`, the name your Wasm module is bound to. Is the JS glue loading the right binding?",
+                binding.name
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+// Imports and features the Workers runtime does not provide.
+fn runtime_warnings(module: &WasmModule) -> Vec<String> {
+    let mut warnings = vec![];
+
+    let wasi: Vec<String> = module
+        .imports
+        .iter()
+        .filter(|i| i.module.starts_with("wasi"))
+        .map(|i| format!("{}.{}", i.module, i.field))
+        .collect();
+    if !wasi.is_empty() {
+        warnings.push(format!(
+            "Your Wasm module imports WASI functions, which are not available on Workers: {}",
+            wasi.join(", ")
+        ));
+    }
+
+    if module.shared_memory {
+        warnings.push(
+            "Your Wasm module uses a shared memory, but threads are not supported on Workers."
+                .to_string(),
+        );
+    }
+
+    warnings
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$'
+}
+
+// Whether `name` appears in `script` as a whole identifier.
+fn references_identifier(script: &str, name: &str) -> bool {
+    script.match_indices(name).any(|(i, _)| {
+        let before = script[..i].chars().next_back();
+        let after = script[i + name.len()..].chars().next();
+        before.map(is_identifier_char) != Some(true) && after.map(is_identifier_char) != Some(true)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wasm::WasmImport;
+
+    #[test]
+    fn it_finds_whole_identifiers() {
+        assert!(references_identifier(
+            "wasm_bindgen(wasmprogram)",
+            "wasmprogram"
+        ));
+        assert!(!references_identifier(
+            "wasm_bindgen(wasmprogram2)",
+            "wasmprogram"
+        ));
+        assert!(!references_identifier("my_wasmprogram", "wasmprogram"));
+    }
+
+    #[test]
+    fn it_warns_about_wasi_and_threads() {
+        let module = WasmModule {
+            size: 0,
+            sections: vec![],
+            functions: vec![],
+            imports: vec![WasmImport {
+                module: "wasi_unstable".to_string(),
+                field: "fd_write".to_string(),
+                kind: "function",
+            }],
+            exports: vec![],
+            shared_memory: true,
+        };
+
+        let warnings = runtime_warnings(&module);
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings[0].contains("wasi_unstable.fd_write"));
+        assert!(warnings[1].contains("threads"));
+    }
+}
@@ -0,0 +1,85 @@
+mod report;
+mod suite;
+
+use std::path::Path;
+use std::time::Instant;
+
+use crate::commands::build::BuildOptions;
+use crate::commands::publish::preview::{self, Target};
+use crate::http;
+use crate::settings::project::Project;
+use crate::terminal::message;
+
+pub use report::Format;
+use report::{Report, TestResult};
+use suite::{Snapshots, Suite};
+
+// Where snapshots are kept, next to the suite file
+const SNAPSHOTS_DIR: &str = "__snapshots__";
+
+#[derive(Debug)]
+pub struct TestOptions {
+    // Test a deployed worker at this URL, rather than a preview of the project
+    pub url: Option<String>,
+    pub format: Format,
+    // Record the snapshots again rather than comparing with them
+    pub update_snapshots: bool,
+}
+
+// Runs the requests of the suite at `path` against a preview of the project,
+// or a deployed worker, and reports which responses were not as expected.
+pub fn test(project: &Project, path: &Path, options: &TestOptions) -> Result<(), failure::Error> {
+    if options.format != Format::Text {
+        message::to_stderr(true);
+    }
+
+    let suite = Suite::load(path)?;
+    let snapshots = Snapshots {
+        dir: path
+            .parent()
+            .unwrap_or_else(|| Path::new("."))
+            .join(SNAPSHOTS_DIR),
+        update: options.update_snapshots,
+    };
+
+    let client = http::client();
+    let target = match &options.url {
+        Some(url) => Target::url(url)?,
+        None => Target::preview(preview::upload(
+            &client,
+            project,
+            None,
+            &BuildOptions::default(),
+        )?),
+    };
+    message::working(&format!("Running {} tests", suite.tests.len()));
+
+    let mut results = vec![];
+    for test in &suite.tests {
+        let start = Instant::now();
+        let failures = match test
+            .request()
+            .and_then(|request| target.send(&client, &request))
+        {
+            Ok(response) => test.check(&response, &snapshots),
+            Err(e) => vec![format!("request failed: {}", e)],
+        };
+        let result = TestResult::new(&test.name, failures, start.elapsed());
+        result.print();
+        results.push(result);
+    }
+
+    let report = Report::new(results);
+    match options.format {
+        Format::Text => {}
+        Format::Json => println!("{}", report.to_json()?),
+        Format::JUnit => print!("{}", report.to_junit(&path.display().to_string())),
+    }
+
+    if report.failed > 0 {
+        failure::bail!("{}", report.summary());
+    }
+    message::success(&report.summary());
+
+    Ok(())
+}
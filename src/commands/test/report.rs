@@ -0,0 +1,168 @@
+use std::str::FromStr;
+use std::time::Duration;
+
+use serde::Serialize;
+
+use crate::terminal::message;
+
+// How the results of a suite are printed.
+#[derive(Debug, PartialEq)]
+pub enum Format {
+    Text,
+    Json,
+    JUnit,
+}
+
+impl FromStr for Format {
+    type Err = failure::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            "junit" => Ok(Format::JUnit),
+            _ => failure::bail!("Unknown output format `{}`", s),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct TestResult {
+    pub name: String,
+    pub passed: bool,
+    pub failures: Vec<String>,
+    #[serde(rename = "time_ms", serialize_with = "millis")]
+    pub elapsed: Duration,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Report {
+    pub tests: Vec<TestResult>,
+    pub passed: usize,
+    pub failed: usize,
+}
+
+fn millis<S: serde::Serializer>(elapsed: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_u64(elapsed.as_millis() as u64)
+}
+
+impl TestResult {
+    pub fn new(name: &str, failures: Vec<String>, elapsed: Duration) -> TestResult {
+        TestResult {
+            name: name.to_string(),
+            passed: failures.is_empty(),
+            failures,
+            elapsed,
+        }
+    }
+
+    // Prints the result as soon as the test has run
+    pub fn print(&self) {
+        let title = format!("{} ({} ms)", self.name, self.elapsed.as_millis());
+        if self.passed {
+            message::success(&title);
+        } else {
+            message::user_error(&title);
+        }
+        for failure in &self.failures {
+            message::detail(&format!("    {}", failure));
+        }
+    }
+}
+
+impl Report {
+    pub fn new(tests: Vec<TestResult>) -> Report {
+        let passed = tests.iter().filter(|t| t.passed).count();
+        Report {
+            failed: tests.len() - passed,
+            passed,
+            tests,
+        }
+    }
+
+    pub fn summary(&self) -> String {
+        format!("{} passed, {} failed", self.passed, self.failed)
+    }
+
+    pub fn to_json(&self) -> Result<String, failure::Error> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    pub fn to_junit(&self, suite: &str) -> String {
+        let time = |elapsed: Duration| format!("{:.3}", elapsed.as_millis() as f64 / 1000.0);
+        let total = self.tests.iter().map(|t| t.elapsed).sum();
+
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        xml.push_str(&format!(
+            "<testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" time=\"{}\">\n",
+            escape(suite),
+            self.tests.len(),
+            self.failed,
+            time(total)
+        ));
+        for test in &self.tests {
+            xml.push_str(&format!(
+                "  <testcase name=\"{}\" classname=\"{}\" time=\"{}\"",
+                escape(&test.name),
+                escape(suite),
+                time(test.elapsed)
+            ));
+            if test.passed {
+                xml.push_str("/>\n");
+                continue;
+            }
+            xml.push_str(">\n");
+            for failure in &test.failures {
+                xml.push_str(&format!("    <failure message=\"{}\"/>\n", escape(failure)));
+            }
+            xml.push_str("  </testcase>\n");
+        }
+        xml.push_str("</testsuite>\n");
+        xml
+    }
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn report() -> Report {
+        Report::new(vec![
+            TestResult::new("home", vec![], Duration::from_millis(12)),
+            TestResult::new(
+                "api <v2>",
+                vec!["expected status 200, got HTTP 404 Not Found".to_string()],
+                Duration::from_millis(1500),
+            ),
+        ])
+    }
+
+    #[test]
+    fn it_counts_results() {
+        let report = report();
+        assert_eq!(report.summary(), "1 passed, 1 failed");
+
+        let json: serde_json::Value = serde_json::from_str(&report.to_json().unwrap()).unwrap();
+        assert_eq!(json["failed"], 1);
+        assert_eq!(json["tests"][0]["time_ms"], 12);
+        assert_eq!(json["tests"][1]["passed"], false);
+    }
+
+    #[test]
+    fn it_renders_junit() {
+        let xml = report().to_junit("suite.toml");
+        assert!(xml
+            .contains("<testsuite name=\"suite.toml\" tests=\"2\" failures=\"1\" time=\"1.512\">"));
+        assert!(xml.contains("<testcase name=\"home\" classname=\"suite.toml\" time=\"0.012\"/>"));
+        assert!(xml.contains("name=\"api &lt;v2&gt;\""));
+        assert!(xml.contains("<failure message=\"expected status 200, got HTTP 404 Not Found\"/>"));
+    }
+}
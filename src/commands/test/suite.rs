@@ -0,0 +1,307 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::commands::publish::preview::{HTTPMethod, PreviewRequest, PreviewResponse};
+
+// A suite of requests and what their responses are expected to be, read from
+// a TOML file of `[[test]]` tables.
+#[derive(Debug, Deserialize)]
+pub struct Suite {
+    #[serde(rename = "test", default)]
+    pub tests: Vec<TestCase>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TestCase {
+    pub name: String,
+    #[serde(default = "default_method")]
+    pub method: String,
+    // A full URL, or only a path and query
+    #[serde(default = "default_url")]
+    pub url: String,
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    pub body: Option<String>,
+    #[serde(default)]
+    pub expect: Expect,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct Expect {
+    pub status: Option<u16>,
+    #[serde(default)]
+    pub headers: BTreeMap<String, Matcher>,
+    pub body: Option<Matcher>,
+}
+
+// How a header or body is matched: a string is matched exactly, and a table
+// picks another matcher.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum Matcher {
+    Exact(String),
+    Regex { regex: String },
+    // The body is JSON, and contains at least the given values
+    Json { json: Value },
+    // The body is the same as the last time it was recorded
+    Snapshot { snapshot: bool },
+}
+
+fn default_method() -> String {
+    "get".to_string()
+}
+
+fn default_url() -> String {
+    "/".to_string()
+}
+
+impl Suite {
+    pub fn load(path: &Path) -> Result<Suite, failure::Error> {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(e) => failure::bail!("Could not read the test suite {}: {}", path.display(), e),
+        };
+        match toml::from_str(&contents) {
+            Ok(suite) => Ok(suite),
+            Err(e) => failure::bail!("Invalid test suite {}: {}", path.display(), e),
+        }
+    }
+}
+
+impl TestCase {
+    pub fn request(&self) -> Result<PreviewRequest, failure::Error> {
+        let headers: Vec<String> = self
+            .headers
+            .iter()
+            .map(|(name, value)| format!("{}: {}", name, value))
+            .collect();
+        let headers: Vec<&str> = headers.iter().map(|h| h.as_str()).collect();
+
+        PreviewRequest::new(
+            HTTPMethod::from_str(&self.method)?,
+            Some(&self.url),
+            false,
+            &headers,
+            self.body.as_ref().map(|b| b.as_bytes().to_vec()),
+        )
+    }
+
+    // What does not match in `response`, as one message per mismatch.
+    // Snapshots are read from and recorded in `snapshots`.
+    pub fn check(&self, response: &PreviewResponse, snapshots: &Snapshots) -> Vec<String> {
+        let mut failures = vec![];
+
+        if let Some(status) = self.expect.status {
+            if response.status.as_u16() != status {
+                failures.push(format!(
+                    "expected status {}, got {}",
+                    status,
+                    response.status_line()
+                ));
+            }
+        }
+
+        for (name, matcher) in &self.expect.headers {
+            let value = response
+                .headers
+                .iter()
+                .filter(|(n, _)| n.eq_ignore_ascii_case(name))
+                .map(|(_, v)| v.as_str())
+                .collect::<Vec<_>>()
+                .join(", ");
+            let snapshot = snapshots.path(&self.name, &name.to_lowercase());
+            if let Err(e) = matcher.check(&value, &snapshot, snapshots.update) {
+                failures.push(format!("header {}: {}", name, e));
+            }
+        }
+
+        if let Some(matcher) = &self.expect.body {
+            let body = String::from_utf8_lossy(&response.body);
+            let snapshot = snapshots.path(&self.name, "body");
+            if let Err(e) = matcher.check(&body, &snapshot, snapshots.update) {
+                failures.push(format!("body: {}", e));
+            }
+        }
+
+        failures
+    }
+}
+
+// Where snapshots are kept, next to the suite, and whether they are being
+// re-recorded.
+pub struct Snapshots {
+    pub dir: PathBuf,
+    pub update: bool,
+}
+
+impl Snapshots {
+    fn path(&self, test: &str, what: &str) -> PathBuf {
+        let name: String = format!("{}.{}", test, what)
+            .chars()
+            .map(|c| {
+                if c.is_alphanumeric() || c == '.' || c == '-' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        self.dir.join(format!("{}.snap", name))
+    }
+}
+
+impl Matcher {
+    pub fn check(&self, actual: &str, snapshot: &Path, update: bool) -> Result<(), String> {
+        match self {
+            Matcher::Exact(expected) => {
+                if actual == expected {
+                    Ok(())
+                } else {
+                    Err(format!("expected {:?}, got {:?}", expected, actual))
+                }
+            }
+            Matcher::Regex { regex } => {
+                let re = Regex::new(regex).map_err(|e| format!("invalid regex: {}", e))?;
+                if re.is_match(actual) {
+                    Ok(())
+                } else {
+                    Err(format!("{:?} does not match /{}/", actual, regex))
+                }
+            }
+            Matcher::Json { json } => {
+                let actual: Value = serde_json::from_str(actual)
+                    .map_err(|e| format!("expected JSON, got {:?} ({})", actual, e))?;
+                json_subset(json, &actual, "$")
+            }
+            Matcher::Snapshot { snapshot: false } => Ok(()),
+            // snapshots are only ever recorded when updating, so that a
+            // missing one fails rather than passes
+            Matcher::Snapshot { snapshot: true } if update => {
+                let recorded = snapshot
+                    .parent()
+                    .map_or(Ok(()), fs::create_dir_all)
+                    .and_then(|_| fs::write(snapshot, actual));
+                recorded.map_err(|e| format!("could not record the snapshot: {}", e))
+            }
+            Matcher::Snapshot { snapshot: true } => match fs::read_to_string(snapshot) {
+                Ok(ref recorded) if recorded == actual => Ok(()),
+                Ok(_) => Err(format!(
+                    "does not match the snapshot {}; got {:?}",
+                    snapshot.display(),
+                    actual
+                )),
+                Err(_) => Err(format!(
+                    "there is no snapshot {}; pass --update-snapshots to record it",
+                    snapshot.display()
+                )),
+            },
+        }
+    }
+}
+
+// Whether `actual` contains everything in `expected`: objects may have more
+// keys, but arrays must have the same elements.
+fn json_subset(expected: &Value, actual: &Value, at: &str) -> Result<(), String> {
+    match (expected, actual) {
+        (Value::Object(expected), Value::Object(actual)) => {
+            for (key, value) in expected {
+                let at = format!("{}.{}", at, key);
+                match actual.get(key) {
+                    Some(actual) => json_subset(value, actual, &at)?,
+                    None => return Err(format!("{} is missing", at)),
+                }
+            }
+            Ok(())
+        }
+        (Value::Array(expected), Value::Array(actual)) if expected.len() == actual.len() => {
+            for (i, (expected, actual)) in expected.iter().zip(actual).enumerate() {
+                json_subset(expected, actual, &format!("{}[{}]", at, i))?;
+            }
+            Ok(())
+        }
+        // TOML and JSON may not agree on integers and floats
+        (Value::Number(expected), Value::Number(actual))
+            if expected.as_f64() == actual.as_f64() =>
+        {
+            Ok(())
+        }
+        (expected, actual) if expected == actual => Ok(()),
+        (expected, actual) => Err(format!("{}: expected {}, got {}", at, expected, actual)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_reads_a_suite() {
+        let suite: Suite = toml::from_str(
+            r#"
+            [[test]]
+            name = "home"
+
+            [[test]]
+            name = "api"
+            method = "post"
+            url = "/api?x=1"
+            headers = { "Content-Type" = "application/json" }
+            body = '{"a":1}'
+            expect = { status = 201, headers = { "content-type" = { regex = "json" } }, body = { json = { ok = true, items = [1, 2] } } }
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(suite.tests.len(), 2);
+        assert_eq!(suite.tests[0].url, "/");
+        assert!(suite.tests[0].expect.body.is_none());
+
+        let api = &suite.tests[1];
+        assert_eq!(api.expect.status, Some(201));
+        match &api.expect.headers["content-type"] {
+            Matcher::Regex { regex } => assert_eq!(regex, "json"),
+            m => panic!("unexpected matcher {:?}", m),
+        }
+        let request = api.request().unwrap();
+        assert_eq!(request.path_and_query(), "/api?x=1");
+        assert_eq!(request.body, Some(b"{\"a\":1}".to_vec()));
+    }
+
+    #[test]
+    fn it_matches_json_subsets() {
+        let expected = serde_json::json!({ "ok": true, "items": [1, { "id": 2 }] });
+        let actual =
+            serde_json::json!({ "ok": true, "extra": 0, "items": [1.0, { "id": 2, "name": "b" }] });
+        assert!(json_subset(&expected, &actual, "$").is_ok());
+
+        let actual = serde_json::json!({ "ok": false, "items": [1, { "id": 2 }] });
+        assert_eq!(
+            json_subset(&expected, &actual, "$"),
+            Err("$.ok: expected true, got false".to_string())
+        );
+    }
+
+    #[test]
+    fn it_records_and_compares_snapshots() {
+        let dir = std::env::temp_dir().join(format!("wrangler-snapshots-{}", std::process::id()));
+        let snapshot = dir.join("test.body.snap");
+        let matcher = Matcher::Snapshot { snapshot: true };
+
+        // a missing snapshot fails until it is recorded
+        assert!(matcher.check("first", &snapshot, false).is_err());
+        assert!(!snapshot.exists());
+        assert!(matcher.check("first", &snapshot, true).is_ok());
+        assert!(matcher.check("first", &snapshot, false).is_ok());
+        assert!(matcher.check("second", &snapshot, false).is_err());
+        assert!(matcher.check("second", &snapshot, true).is_ok());
+        assert!(matcher.check("second", &snapshot, false).is_ok());
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
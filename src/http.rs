@@ -32,6 +32,17 @@ pub fn client() -> Client {
         .expect("could not create http client")
 }
 
+// A client that passes responses through as they are, without following
+// redirects or decompressing bodies.
+pub fn proxy_client() -> Client {
+    builder()
+        .default_headers(headers())
+        .redirect(RedirectPolicy::none())
+        .gzip(false)
+        .build()
+        .expect("could not create proxy http client")
+}
+
 pub fn auth_client(user: &GlobalUser) -> Client {
     let mut headers = headers();
     headers.insert("X-Auth-Key", HeaderValue::from_str(&user.api_key).unwrap());
@@ -6,8 +6,11 @@ use krate::Krate;
 use log::info;
 use which::which;
 
+use crate::terminal::message;
+
 use std::env;
 use std::path::Path;
+use std::process::Command;
 
 use lazy_static::lazy_static;
 
@@ -49,6 +52,60 @@ pub fn install_artifact(
     }
 }
 
+// Installs binaryen, whose tools are released together at `version` rather
+// than published as crates. A `binary` already on the PATH is used instead.
+pub fn install_binaryen(binary: &str, version: &str) -> Result<Download, failure::Error> {
+    if let Some(download) = tool_exists(binary) {
+        return Ok(download);
+    }
+
+    let tool_name = "binaryen";
+    let download = download_prebuilt(tool_name, "WebAssembly", version, &[binary]);
+    match download {
+        Ok(download) => Ok(download),
+        Err(e) => {
+            failure::bail!("could not download pre-built `{}` ({}).", tool_name, e);
+        }
+    }
+}
+
+// Installs `version` of `tool_name`, for tools that must match a library
+// exactly. The one on the PATH is only used if it is that version.
+pub fn install_version(
+    tool_name: &str,
+    owner: &str,
+    version: &str,
+) -> Result<Download, failure::Error> {
+    if let Some(download) = tool_exists(tool_name) {
+        if tool_version(&download, tool_name) == Some(version.to_string()) {
+            return Ok(download);
+        }
+    }
+
+    let download = download_prebuilt(tool_name, owner, version, &[tool_name]);
+    match download {
+        Ok(download) => Ok(download),
+        Err(e) => {
+            failure::bail!(
+                "could not download pre-built `{}` {} ({}).",
+                tool_name,
+                version,
+                e
+            );
+        }
+    }
+}
+
+// The version `tool_name --version` reports, as in `wasm-bindgen 0.2.50`
+fn tool_version(download: &Download, tool_name: &str) -> Option<String> {
+    let binary = download.binary(tool_name).ok()?;
+    let output = Command::new(binary).arg("--version").output().ok()?;
+    String::from_utf8_lossy(&output.stdout)
+        .split_whitespace()
+        .nth(1)
+        .map(str::to_string)
+}
+
 fn tool_exists(tool_name: &str) -> Option<Download> {
     if let Ok(path) = which(tool_name) {
         log::debug!("found global {} binary at: {}", tool_name, path.display());
@@ -85,7 +142,7 @@ fn download_prebuilt(
 
     match res {
         Some(download) => {
-            println!("⬇️ Installing {}...", tool_name);
+            message::detail(&format!("⬇️ Installing {}...", tool_name));
             Ok(download)
         }
         None => failure::bail!("{} is not installed!", tool_name),
@@ -98,6 +155,39 @@ fn prebuilt_url(tool_name: &str, owner: &str, version: &str) -> Option<String> {
             "https://github.com/cloudflare/wrangler/releases/download/v{1}/{0}-v{1}.tar.gz",
             tool_name, version
         ))
+    } else if tool_name == "wasm-bindgen" {
+        let target = if target::LINUX && target::x86_64 {
+            "x86_64-unknown-linux-musl"
+        } else if target::MACOS && target::x86_64 {
+            "x86_64-apple-darwin"
+        } else if target::WINDOWS && target::x86_64 {
+            "x86_64-pc-windows-msvc"
+        } else {
+            return None;
+        };
+
+        // wasm-bindgen releases are tagged without a `v`
+        let url = format!(
+            "https://github.com/{0}/{1}/releases/download/{2}/{1}-{2}-{3}.tar.gz",
+            owner, tool_name, version, target
+        );
+        Some(url)
+    } else if tool_name == "binaryen" {
+        let target = if target::LINUX && target::x86_64 {
+            "x86_64-linux"
+        } else if target::MACOS && target::x86_64 {
+            "x86_64-macos"
+        } else if target::WINDOWS && target::x86_64 {
+            "x86_64-windows"
+        } else {
+            return None;
+        };
+
+        let url = format!(
+            "https://github.com/{0}/{1}/releases/download/version_{2}/{1}-version_{2}-{3}.tar.gz",
+            owner, tool_name, version, target
+        );
+        Some(url)
     } else {
         let target = if target::LINUX && target::x86_64 {
             "x86_64-unknown-linux-musl"
@@ -0,0 +1,694 @@
+//! Inspection of the JavaScript that `wrangler` uploads.
+//!
+//! This is not a parser: it tokenizes a script and uses a few heuristics to
+//! tell which identifiers are declared somewhere in it and which are only
+//! ever referenced. Scopes are ignored, so a name declared anywhere counts as
+//! declared everywhere; this errs on the side of reporting fewer free
+//! identifiers.
+
+use std::collections::{BTreeSet, HashSet};
+
+// Reserved words, literals and contextual keywords, which are never free
+// identifiers.
+const KEYWORDS: &[&str] = &[
+    "as",
+    "async",
+    "await",
+    "break",
+    "case",
+    "catch",
+    "class",
+    "const",
+    "continue",
+    "debugger",
+    "default",
+    "delete",
+    "do",
+    "else",
+    "enum",
+    "export",
+    "extends",
+    "false",
+    "finally",
+    "for",
+    "from",
+    "function",
+    "get",
+    "if",
+    "implements",
+    "import",
+    "in",
+    "instanceof",
+    "interface",
+    "let",
+    "new",
+    "null",
+    "of",
+    "package",
+    "private",
+    "protected",
+    "public",
+    "return",
+    "set",
+    "static",
+    "super",
+    "switch",
+    "this",
+    "throw",
+    "true",
+    "try",
+    "typeof",
+    "var",
+    "void",
+    "while",
+    "with",
+    "yield",
+];
+
+// Keywords after which a `/` starts a regular expression rather than a
+// division.
+const REGEX_PREFIX_KEYWORDS: &[&str] = &[
+    "case",
+    "delete",
+    "do",
+    "else",
+    "in",
+    "instanceof",
+    "new",
+    "of",
+    "return",
+    "throw",
+    "typeof",
+    "void",
+    "yield",
+];
+
+// Globals of the JavaScript language itself.
+pub const LANGUAGE_GLOBALS: &[&str] = &[
+    "Array",
+    "ArrayBuffer",
+    "Atomics",
+    "BigInt",
+    "BigInt64Array",
+    "BigUint64Array",
+    "Boolean",
+    "DataView",
+    "Date",
+    "Error",
+    "EvalError",
+    "Float32Array",
+    "Float64Array",
+    "Function",
+    "Infinity",
+    "Int16Array",
+    "Int32Array",
+    "Int8Array",
+    "Intl",
+    "JSON",
+    "Map",
+    "Math",
+    "NaN",
+    "Number",
+    "Object",
+    "Promise",
+    "Proxy",
+    "RangeError",
+    "ReferenceError",
+    "Reflect",
+    "RegExp",
+    "Set",
+    "SharedArrayBuffer",
+    "String",
+    "Symbol",
+    "SyntaxError",
+    "TypeError",
+    "URIError",
+    "Uint16Array",
+    "Uint32Array",
+    "Uint8Array",
+    "Uint8ClampedArray",
+    "WeakMap",
+    "WeakSet",
+    "WebAssembly",
+    "arguments",
+    "decodeURI",
+    "decodeURIComponent",
+    "encodeURI",
+    "encodeURIComponent",
+    "escape",
+    "eval",
+    "globalThis",
+    "isFinite",
+    "isNaN",
+    "parseFloat",
+    "parseInt",
+    "undefined",
+    "unescape",
+];
+
+// Globals provided by the Workers runtime.
+pub const WORKERS_GLOBALS: &[&str] = &[
+    "AbortController",
+    "AbortSignal",
+    "Blob",
+    "Cache",
+    "CacheStorage",
+    "Crypto",
+    "CryptoKey",
+    "Event",
+    "EventTarget",
+    "FetchEvent",
+    "File",
+    "FormData",
+    "Headers",
+    "ReadableStream",
+    "Request",
+    "Response",
+    "SubtleCrypto",
+    "TextDecoder",
+    "TextEncoder",
+    "TransformStream",
+    "URL",
+    "URLSearchParams",
+    "WritableStream",
+    "addEventListener",
+    "atob",
+    "btoa",
+    "caches",
+    "clearInterval",
+    "clearTimeout",
+    "console",
+    "crypto",
+    "dispatchEvent",
+    "fetch",
+    "removeEventListener",
+    "self",
+    "setInterval",
+    "setTimeout",
+];
+
+#[derive(Debug, PartialEq)]
+enum Token<'a> {
+    Ident(&'a str),
+    Punct(&'a str),
+    Literal,
+}
+
+struct Tokenizer<'a> {
+    src: &'a str,
+    bytes: &'a [u8],
+    pos: usize,
+    tokens: Vec<Token<'a>>,
+}
+
+fn is_ident_start(b: u8) -> bool {
+    b.is_ascii_alphabetic() || b == b'_' || b == b'$' || b >= 0x80
+}
+
+fn is_ident_part(b: u8) -> bool {
+    is_ident_start(b) || b.is_ascii_digit()
+}
+
+impl<'a> Tokenizer<'a> {
+    fn new(src: &'a str) -> Tokenizer<'a> {
+        Tokenizer {
+            src,
+            bytes: src.as_bytes(),
+            pos: 0,
+            tokens: vec![],
+        }
+    }
+
+    fn peek(&self, offset: usize) -> Option<u8> {
+        self.bytes.get(self.pos + offset).cloned()
+    }
+
+    // Tokenizes until the end of the input or, inside a template literal
+    // substitution, until its closing brace.
+    fn scan(&mut self, in_template: bool) {
+        let mut depth = 0;
+        while let Some(b) = self.peek(0) {
+            match b {
+                b if b.is_ascii_whitespace() => self.pos += 1,
+                b'/' if self.peek(1) == Some(b'/') => {
+                    while self.peek(0).map(|b| b != b'\n') == Some(true) {
+                        self.pos += 1;
+                    }
+                }
+                b'/' if self.peek(1) == Some(b'*') => {
+                    self.pos += 2;
+                    while self.pos < self.bytes.len()
+                        && !(self.peek(0) == Some(b'*') && self.peek(1) == Some(b'/'))
+                    {
+                        self.pos += 1;
+                    }
+                    self.pos += 2;
+                }
+                b'/' if self.regex_allowed() => self.regex(),
+                b'\'' | b'"' => self.string(b),
+                b'`' => self.template(),
+                b if b.is_ascii_digit() || (b == b'.' && self.next_is_digit()) => self.number(),
+                b if is_ident_start(b) => self.ident(),
+                b'{' => {
+                    depth += 1;
+                    self.punct(1);
+                }
+                b'}' => {
+                    if in_template && depth == 0 {
+                        self.pos += 1;
+                        return;
+                    }
+                    depth -= 1;
+                    self.punct(1);
+                }
+                b'=' if self.peek(1) == Some(b'>') => self.punct(2),
+                b'.' if self.peek(1) == Some(b'.') && self.peek(2) == Some(b'.') => self.punct(3),
+                _ => self.punct(1),
+            }
+        }
+    }
+
+    fn next_is_digit(&self) -> bool {
+        self.peek(1).map(|b| b.is_ascii_digit()) == Some(true)
+    }
+
+    fn regex_allowed(&self) -> bool {
+        match self.tokens.last() {
+            None => true,
+            Some(Token::Punct(p)) => !(*p == ")" || *p == "]" || *p == "}"),
+            Some(Token::Ident(i)) => REGEX_PREFIX_KEYWORDS.contains(i),
+            Some(Token::Literal) => false,
+        }
+    }
+
+    fn punct(&mut self, len: usize) {
+        let end = (self.pos + len).min(self.bytes.len());
+        // never split a multi-byte character
+        let end = (end..=self.bytes.len())
+            .find(|&e| self.src.is_char_boundary(e))
+            .unwrap_or(self.bytes.len());
+        self.tokens.push(Token::Punct(&self.src[self.pos..end]));
+        self.pos = end;
+    }
+
+    fn ident(&mut self) {
+        let start = self.pos;
+        while self.peek(0).map(is_ident_part) == Some(true) {
+            self.pos += 1;
+        }
+        self.tokens.push(Token::Ident(&self.src[start..self.pos]));
+    }
+
+    fn number(&mut self) {
+        while self.peek(0).map(|b| is_ident_part(b) || b == b'.') == Some(true) {
+            self.pos += 1;
+        }
+        self.tokens.push(Token::Literal);
+    }
+
+    fn string(&mut self, quote: u8) {
+        self.pos += 1;
+        while let Some(b) = self.peek(0) {
+            self.pos += 1;
+            if b == b'\\' {
+                self.pos += 1;
+            } else if b == quote || b == b'\n' {
+                break;
+            }
+        }
+        self.tokens.push(Token::Literal);
+    }
+
+    fn template(&mut self) {
+        self.pos += 1;
+        while let Some(b) = self.peek(0) {
+            if b == b'\\' {
+                self.pos += 2;
+            } else if b == b'`' {
+                self.pos += 1;
+                break;
+            } else if b == b'$' && self.peek(1) == Some(b'{') {
+                self.pos += 2;
+                self.scan(true);
+            } else {
+                self.pos += 1;
+            }
+        }
+        self.tokens.push(Token::Literal);
+    }
+
+    fn regex(&mut self) {
+        self.pos += 1;
+        let mut in_class = false;
+        while let Some(b) = self.peek(0) {
+            self.pos += 1;
+            match b {
+                b'\\' => self.pos += 1,
+                b'[' => in_class = true,
+                b']' => in_class = false,
+                b'/' if !in_class => break,
+                b'\n' => break,
+                _ => {}
+            }
+        }
+        // flags
+        while self.peek(0).map(is_ident_part) == Some(true) {
+            self.pos += 1;
+        }
+        self.tokens.push(Token::Literal);
+    }
+}
+
+fn tokenize(src: &str) -> Vec<Token<'_>> {
+    let mut tokenizer = Tokenizer::new(src);
+    tokenizer.scan(false);
+    tokenizer.tokens
+}
+
+fn is_punct(token: Option<&Token>, p: &str) -> bool {
+    token == Some(&Token::Punct(p))
+}
+
+fn is_keyword(ident: &str) -> bool {
+    KEYWORDS.contains(&ident)
+}
+
+// Index of the token closing the bracket opened at `open`.
+fn matching(tokens: &[Token], open: usize) -> Option<usize> {
+    let mut depth = 0;
+    for (i, token) in tokens.iter().enumerate().skip(open) {
+        match token {
+            Token::Punct("(") | Token::Punct("[") | Token::Punct("{") => depth += 1,
+            Token::Punct(")") | Token::Punct("]") | Token::Punct("}") => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+// Index of the token opening the bracket closed at `close`.
+fn matching_back(tokens: &[Token], close: usize) -> Option<usize> {
+    let mut depth = 0;
+    for i in (0..=close).rev() {
+        match tokens[i] {
+            Token::Punct(")") | Token::Punct("]") | Token::Punct("}") => depth += 1,
+            Token::Punct("(") | Token::Punct("[") | Token::Punct("{") => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+// The identifiers bound by a parameter list or destructuring pattern between
+// `start` and `end`; property keys and default values are skipped.
+fn pattern_names<'a>(tokens: &[Token<'a>], start: usize, end: usize, names: &mut HashSet<&'a str>) {
+    let mut depth = 0;
+    // the depth of the pattern a default value is in, which only runs until
+    // the next element of that pattern, or its end
+    let mut default_depth = None;
+    for i in start..end {
+        match tokens[i] {
+            Token::Punct("(") | Token::Punct("[") | Token::Punct("{") => depth += 1,
+            Token::Punct(")") | Token::Punct("]") | Token::Punct("}") => {
+                depth -= 1;
+                if matches!(default_depth, Some(d) if depth < d) {
+                    default_depth = None;
+                }
+            }
+            Token::Punct("=") if default_depth.is_none() => default_depth = Some(depth),
+            Token::Punct(",") if default_depth == Some(depth) => default_depth = None,
+            Token::Ident(ident)
+                if default_depth.is_none()
+                    && !is_keyword(ident)
+                    && !is_punct(tokens.get(i + 1), ":") =>
+            {
+                names.insert(ident);
+            }
+            _ => {}
+        }
+    }
+}
+
+// The identifiers declared by a `var`, `let` or `const` starting at `start`.
+fn declaration_names<'a>(tokens: &[Token<'a>], start: usize, names: &mut HashSet<&'a str>) {
+    let mut i = start;
+    loop {
+        match tokens.get(i) {
+            Some(Token::Ident(ident)) if !is_keyword(ident) => {
+                names.insert(ident);
+            }
+            Some(Token::Punct("{")) | Some(Token::Punct("[")) => {
+                let end = match matching(tokens, i) {
+                    Some(end) => end,
+                    None => return,
+                };
+                pattern_names(tokens, i, end, names);
+                i = end;
+            }
+            _ => return,
+        }
+
+        // skip the initializer, up to the next declarator
+        i += 1;
+        let mut depth = 0;
+        loop {
+            match tokens.get(i) {
+                None => return,
+                Some(Token::Punct("(")) | Some(Token::Punct("[")) | Some(Token::Punct("{")) => {
+                    depth += 1
+                }
+                Some(Token::Punct(")")) | Some(Token::Punct("]")) | Some(Token::Punct("}")) => {
+                    if depth == 0 {
+                        return;
+                    }
+                    depth -= 1
+                }
+                Some(Token::Punct(",")) if depth == 0 => break,
+                Some(Token::Punct(";")) if depth == 0 => return,
+                // `for (const x of xs)`
+                Some(Token::Ident("in")) | Some(Token::Ident("of")) if depth == 0 => return,
+                _ => {}
+            }
+            i += 1;
+        }
+        i += 1;
+    }
+}
+
+// The names a script declares and the names it references, as far as the
+// heuristics can tell.
+#[derive(Debug, Default)]
+pub struct Identifiers {
+    pub declared: BTreeSet<String>,
+    pub referenced: BTreeSet<String>,
+}
+
+impl Identifiers {
+    pub fn scan(script: &str) -> Identifiers {
+        let tokens = tokenize(script);
+        let mut declared = HashSet::new();
+        // identifiers in positions that are not references
+        let mut not_references = HashSet::new();
+
+        for (i, token) in tokens.iter().enumerate() {
+            let ident = match token {
+                Token::Ident(ident) => *ident,
+                Token::Punct("=>") => {
+                    match tokens.get(i.wrapping_sub(1)) {
+                        Some(Token::Ident(param)) => {
+                            declared.insert(*param);
+                        }
+                        Some(Token::Punct(")")) => {
+                            if let Some(open) = matching_back(&tokens, i - 1) {
+                                pattern_names(&tokens, open, i - 1, &mut declared);
+                            }
+                        }
+                        _ => {}
+                    }
+                    continue;
+                }
+                _ => continue,
+            };
+            let next = tokens.get(i + 1);
+
+            match ident {
+                "var" | "let" | "const" => declaration_names(&tokens, i + 1, &mut declared),
+                // a global assigned as a property of the global object, as
+                // the wasm-bindgen glue does with `self.wasm_bindgen = ...`
+                "self" | "globalThis" if is_punct(next, ".") => {
+                    if let Some(Token::Ident(name)) = tokens.get(i + 2) {
+                        if is_punct(tokens.get(i + 3), "=") && !is_punct(tokens.get(i + 4), "=") {
+                            declared.insert(name);
+                        }
+                    }
+                }
+                "function" | "class" => {
+                    let mut params = i + 1;
+                    if let Some(Token::Ident(name)) = next {
+                        declared.insert(name);
+                        params += 1;
+                    }
+                    if ident == "function" && is_punct(tokens.get(params), "(") {
+                        if let Some(close) = matching(&tokens, params) {
+                            pattern_names(&tokens, params, close, &mut declared);
+                        }
+                    }
+                }
+                "catch" if is_punct(next, "(") => {
+                    if let Some(close) = matching(&tokens, i + 1) {
+                        pattern_names(&tokens, i + 1, close, &mut declared);
+                    }
+                }
+                _ if !is_keyword(ident) && is_punct(next, "(") => {
+                    // a method definition, `name(params) { ... }`
+                    if let Some(close) = matching(&tokens, i + 1) {
+                        if is_punct(tokens.get(close + 1), "{") {
+                            not_references.insert(i);
+                            pattern_names(&tokens, i + 1, close, &mut declared);
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let mut referenced = BTreeSet::new();
+        for (i, token) in tokens.iter().enumerate() {
+            let ident = match token {
+                Token::Ident(ident) if !is_keyword(ident) => *ident,
+                _ => continue,
+            };
+            let previous = if i > 0 { tokens.get(i - 1) } else { None };
+            let next = tokens.get(i + 1);
+
+            // property accesses and safe `typeof` checks
+            if is_punct(previous, ".") || previous == Some(&Token::Ident("typeof")) {
+                continue;
+            }
+            // object keys and labels, but not the middle of a ternary
+            if is_punct(next, ":")
+                && (previous.is_none()
+                    || is_punct(previous, "{")
+                    || is_punct(previous, ",")
+                    || is_punct(previous, ";")
+                    || is_punct(previous, "}"))
+            {
+                continue;
+            }
+            if not_references.contains(&i) {
+                continue;
+            }
+            referenced.insert(ident.to_string());
+        }
+
+        Identifiers {
+            declared: declared.into_iter().map(|d| d.to_string()).collect(),
+            referenced,
+        }
+    }
+
+    // Referenced names that are neither declared in the script nor globals
+    // of the language or of the Workers runtime.
+    pub fn free(&self) -> BTreeSet<String> {
+        self.referenced
+            .iter()
+            .filter(|r| !self.declared.contains(*r))
+            .filter(|r| !LANGUAGE_GLOBALS.contains(&r.as_str()))
+            .filter(|r| !WORKERS_GLOBALS.contains(&r.as_str()))
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn free(script: &str) -> Vec<String> {
+        Identifiers::scan(script).free().into_iter().collect()
+    }
+
+    #[test]
+    fn it_finds_free_identifiers() {
+        let script = r#"
+            addEventListener('fetch', event => {
+                event.respondWith(handle(event.request))
+            })
+            async function handle(request) {
+                const { pathname: path, search } = new URL(request.url);
+                let value = await MY_KV.get(path + search);
+                return new Response(value || DEFAULT_VALUE, { status: 200 })
+            }
+        "#;
+
+        assert_eq!(free(script), vec!["DEFAULT_VALUE", "MY_KV"]);
+    }
+
+    #[test]
+    fn it_skips_strings_comments_and_regexes() {
+        let script = r#"
+            // NOT_A_BINDING
+            /* ALSO_NOT */
+            var a = "STRING", b = 'OTHER', c = /REGEX[/]x/g.test(a);
+            var t = `template ${TEMPLATE_BINDING} LITERAL`;
+        "#;
+
+        assert_eq!(free(script), vec!["TEMPLATE_BINDING"]);
+    }
+
+    #[test]
+    fn it_skips_object_keys_methods_and_typeof() {
+        let script = r#"
+            const o = { key: 1, method(arg) { return arg }, other: x ? y : z };
+            if (typeof document !== 'undefined') {}
+            class Foo { bar(baz) { return baz } }
+        "#;
+
+        assert_eq!(free(script), vec!["x", "y", "z"]);
+    }
+
+    #[test]
+    fn it_declares_parameters_of_every_kind() {
+        let script = r#"
+            function f(a, { b, c: d }, [e], g = DEFAULT) { return a + b + d + e + g }
+            const h = (i, j) => i + j;
+            try {} catch (err) { err }
+            for (const k of []) { k }
+        "#;
+
+        assert_eq!(free(script), vec!["DEFAULT"]);
+    }
+
+    #[test]
+    fn it_skips_nested_default_values() {
+        let script = r#"
+            function f(a = g(x, MY_KV), [b = [1, OTHER]], { c = { d: 1, e } } = {}) {
+                return a + b + c
+            }
+        "#;
+
+        assert_eq!(free(script), vec!["MY_KV", "OTHER", "e", "g", "x"]);
+    }
+
+    #[test]
+    fn it_declares_properties_of_the_global_object() {
+        let script = r#"
+            self.wasm_bindgen = Object.assign(init, {});
+            globalThis.CONFIG = {};
+            if (self.UNSET == null) {}
+            wasm_bindgen(CONFIG, UNSET)
+        "#;
+
+        assert_eq!(free(script), vec!["UNSET", "init"]);
+    }
+}
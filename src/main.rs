@@ -1,9 +1,10 @@
 #![allow(clippy::redundant_closure)]
 
 use std::env;
+use std::path::Path;
 use std::str::FromStr;
 
-use clap::{App, AppSettings, Arg, SubCommand};
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
 use commands::HTTPMethod;
 
 use log::info;
@@ -12,8 +13,10 @@ mod commands;
 mod http;
 mod install;
 mod installer;
+mod js;
 mod settings;
 mod terminal;
+mod wasm;
 
 use crate::settings::project::ProjectType;
 use terminal::emoji;
@@ -86,8 +89,87 @@ fn main() -> Result<(), failure::Error> {
                 .about(&*format!(
                     "{} Build your worker",
                     emoji::CRAB
+                ))
+                .arg(
+                    Arg::with_name("analyze")
+                        .long("analyze")
+                        .takes_value(false)
+                        .help("report what makes up your built worker and how it changed since the last analysis"),
+                )
+                .arg(
+                    Arg::with_name("watch")
+                        .long("watch")
+                        .takes_value(false)
+                        .conflicts_with("analyze")
+                        .help("Build your project again whenever its sources change"),
+                )
+                .arg(
+                    Arg::with_name("profile")
+                        .long("profile")
+                        .takes_value(true)
+                        .possible_values(&["dev", "release", "profiling"])
+                        .help("Profile to build a Rust project with, instead of the one in wrangler.toml"),
+                )
+                .arg(
+                    Arg::with_name("features")
+                        .long("features")
+                        .takes_value(true)
+                        .multiple(true)
+                        .use_delimiter(true)
+                        .number_of_values(1)
+                        .help("Cargo features to enable for a Rust project, instead of the ones in wrangler.toml"),
+                )
+                .arg(
+                    Arg::with_name("wasm-pack-arg")
+                        .long("wasm-pack-arg")
+                        .value_name("ARG")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                        .allow_hyphen_values(true)
+                        .help("Argument to add to `wasm-pack build` for a Rust project; can be repeated"),
+                )
+                .arg(
+                    Arg::with_name("out-dir")
+                        .long("out-dir")
+                        .value_name("DIR")
+                        .takes_value(true)
+                        .help("Directory wasm-pack writes a Rust project to, instead of the one in wrangler.toml"),
+                )
+                .arg(
+                    Arg::with_name("backend")
+                        .long("backend")
+                        .takes_value(true)
+                        .possible_values(&["wasm-pack", "cargo"])
+                        .help("Build a Rust project with wasm-pack, or with cargo and wasm-bindgen, instead of as set in wrangler.toml"),
                 )
-            ),
+                .arg(
+                    Arg::with_name("mode")
+                        .long("mode")
+                        .takes_value(true)
+                        .possible_values(&["development", "production", "none"])
+                        .help("Mode to build a webpack project in, instead of the one in wrangler.toml"),
+                )
+                .arg(
+                    Arg::with_name("wasm-opt")
+                        .long("wasm-opt")
+                        .value_name("LEVEL")
+                        .takes_value(true)
+                        .possible_values(&["0", "1", "2", "3", "4", "s", "z"])
+                        .help("Optimize the built Wasm with wasm-opt at this level, instead of the one in wrangler.toml"),
+                )
+                .arg(
+                    Arg::with_name("wasm-opt-strip")
+                        .long("wasm-opt-strip")
+                        .takes_value(false)
+                        .help("Optimize the built Wasm with wasm-opt, and strip its name and custom sections"),
+                )
+                .arg(
+                    Arg::with_name("force-build")
+                        .long("force-build")
+                        .takes_value(false)
+                        .help("Build your project even if nothing changed since the last build"),
+                ),
         )
         .subcommand(
             SubCommand::with_name("preview")
@@ -95,15 +177,197 @@ fn main() -> Result<(), failure::Error> {
                     "{} Preview your code temporarily on cloudflareworkers.com",
                     emoji::MICROSCOPE
                 ))
+                .args(&request_args())
                 .arg(
-                    Arg::with_name("method")
-                        .help("Type of request to preview your worker with (get, post)")
-                        .index(1),
+                    Arg::with_name("force-build")
+                        .long("force-build")
+                        .takes_value(false)
+                        .help("Build your project even if nothing changed since the last build"),
                 )
                 .arg(
-                    Arg::with_name("body")
-                        .help("Body string to post to your preview worker request")
-                        .index(2),
+                    Arg::with_name("verbose")
+                        .long("verbose")
+                        .short("v")
+                        .takes_value(false)
+                        .help("Print the request sent to your worker as well as its response"),
+                )
+                .arg(
+                    Arg::with_name("json")
+                        .long("json")
+                        .takes_value(false)
+                        .help("Print the response as JSON, with a base64 body if it is binary"),
+                )
+                .arg(
+                    Arg::with_name("headless")
+                        .long("headless")
+                        .visible_alias("no-open")
+                        .takes_value(false)
+                        .help("Print the preview URL without opening it in a browser"),
+                )
+                .arg(
+                    Arg::with_name("watch")
+                        .long("watch")
+                        .takes_value(false)
+                        .help("Update the preview and replay the request whenever your sources change"),
+                )
+                .arg(
+                    Arg::with_name("compare")
+                        .long("compare")
+                        .takes_value(true)
+                        .value_name("URL")
+                        .conflicts_with("watch")
+                        .help("Send the request to your worker in production too, and report where the responses differ"),
+                )
+                .arg(
+                    Arg::with_name("paths")
+                        .long("paths")
+                        .takes_value(true)
+                        .value_name("FILE")
+                        .requires("compare")
+                        .help("File of paths to compare, one per line, instead of the request URL"),
+                )
+                .arg(
+                    Arg::with_name("ignore-header")
+                        .long("ignore-header")
+                        .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
+                        .requires("compare")
+                        .help("Header to leave out of the comparison, as well as the ones set by the edge"),
+                )
+                .arg(
+                    Arg::with_name("har")
+                        .long("har")
+                        .takes_value(true)
+                        .value_name("FILE")
+                        .conflicts_with_all(&["watch", "compare"])
+                        .help("Replay the requests recorded in a HAR file against the preview"),
+                )
+                .arg(
+                    Arg::with_name("save-responses")
+                        .long("save-responses")
+                        .takes_value(true)
+                        .value_name("DIR")
+                        .requires("har")
+                        .help("Directory to save each replayed response to, as JSON"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("dev")
+                .about(&*format!(
+                    "{} Serve your worker locally, through the preview service",
+                    emoji::WORKER
+                ))
+                .arg(
+                    Arg::with_name("host")
+                        .long("host")
+                        .takes_value(true)
+                        .default_value("127.0.0.1")
+                        .help("Host to listen on"),
+                )
+                .arg(
+                    Arg::with_name("port")
+                        .long("port")
+                        .short("p")
+                        .takes_value(true)
+                        .default_value("8787")
+                        .help("Port to listen on"),
+                )
+                .arg(
+                    Arg::with_name("local")
+                        .long("local")
+                        .takes_value(false)
+                        .help("Run your worker in a local emulator, without the preview service"),
+                )
+                .arg(
+                    Arg::with_name("har")
+                        .long("har")
+                        .takes_value(true)
+                        .value_name("FILE")
+                        .help("Replay the requests recorded in a HAR file against your worker once it is served"),
+                )
+                .arg(
+                    Arg::with_name("save-responses")
+                        .long("save-responses")
+                        .takes_value(true)
+                        .value_name("DIR")
+                        .requires("har")
+                        .help("Directory to save each replayed response to, as JSON"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("bench")
+                .about(&*format!(
+                    "{} Measure the latency and throughput of your worker",
+                    emoji::MICROSCOPE
+                ))
+                .args(&request_args())
+                .arg(
+                    Arg::with_name("requests")
+                        .long("requests")
+                        .short("n")
+                        .takes_value(true)
+                        .default_value("100")
+                        .help("Number of requests to send"),
+                )
+                .arg(
+                    Arg::with_name("concurrency")
+                        .long("concurrency")
+                        .short("c")
+                        .takes_value(true)
+                        .default_value("10")
+                        .help("Number of requests in flight at a time"),
+                )
+                .arg(
+                    Arg::with_name("deployed")
+                        .long("deployed")
+                        .takes_value(false)
+                        .requires("url")
+                        .help("Send the requests to the URL itself rather than to a preview of your project"),
+                )
+                .arg(
+                    Arg::with_name("force-build")
+                        .long("force-build")
+                        .takes_value(false)
+                        .help("Build your project even if nothing changed since the last build"),
+                )
+                .arg(
+                    Arg::with_name("json")
+                        .long("json")
+                        .takes_value(false)
+                        .help("Print the results as JSON"),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("test")
+                .about(&*format!(
+                    "{} Check the responses of your worker against a suite of requests",
+                    emoji::MICROSCOPE
+                ))
+                .arg(
+                    Arg::with_name("suite")
+                        .required(true)
+                        .help("TOML file of requests and the responses they should get"),
+                )
+                .arg(
+                    Arg::with_name("url")
+                        .long("url")
+                        .takes_value(true)
+                        .help("Run the suite against a deployed worker rather than a preview"),
+                )
+                .arg(
+                    Arg::with_name("format")
+                        .long("format")
+                        .takes_value(true)
+                        .possible_values(&["text", "json", "junit"])
+                        .default_value("text")
+                        .help("How to report the results"),
+                )
+                .arg(
+                    Arg::with_name("update-snapshots")
+                        .long("update-snapshots")
+                        .takes_value(false)
+                        .help("Record the snapshots again rather than comparing with them"),
                 ),
         )
         .subcommand(
@@ -116,6 +380,18 @@ fn main() -> Result<(), failure::Error> {
                     .long("release")
                     .takes_value(false)
                     .help("should this be published to a workers.dev subdomain or a domain name you have registered"),
+             )
+            .arg(
+                Arg::with_name("force")
+                    .long("force")
+                    .takes_value(false)
+                    .help("upload your script even if it is unchanged since the last publish"),
+             )
+            .arg(
+                Arg::with_name("force-build")
+                    .long("force-build")
+                    .takes_value(false)
+                    .help("build your project even if nothing changed since the last build"),
              ),
         )
         .subcommand(
@@ -193,22 +469,150 @@ fn main() -> Result<(), failure::Error> {
             None => None,
         };
         commands::init(name, project_type)?;
-    } else if matches.subcommand_matches("build").is_some() {
+    } else if let Some(matches) = matches.subcommand_matches("build") {
         info!("Getting project settings");
-        let project = settings::project::Project::new()?;
-        commands::build(&project)?;
+        let mut project = settings::project::Project::new()?;
+
+        // flags take the place of the `[build.rust]` settings they match
+        let mut rust = project.rust_build();
+        let values = |name| {
+            matches
+                .values_of(name)
+                .map(|v| v.map(|v| v.to_string()).collect::<Vec<_>>())
+        };
+        if let Some(profile) = matches.value_of("profile") {
+            rust.profile = Some(profile.to_string());
+        }
+        if let Some(features) = values("features") {
+            rust.features = Some(features);
+        }
+        if let Some(args) = values("wasm-pack-arg") {
+            rust.wasm_pack_args = Some(args);
+        }
+        if let Some(out_dir) = matches.value_of("out-dir") {
+            rust.out_dir = Some(out_dir.to_string());
+        }
+        if let Some(backend) = matches.value_of("backend") {
+            rust.backend = Some(backend.to_string());
+        }
+        project.set_rust_build(rust);
+
+        if let Some(mode) = matches.value_of("mode") {
+            let mut webpack = project.webpack_build();
+            webpack.mode = Some(mode.to_string());
+            project.set_webpack_build(webpack);
+        }
+
+        // either wasm-opt flag turns the optimization on
+        if matches.is_present("wasm-opt") || matches.is_present("wasm-opt-strip") {
+            let mut wasm_opt = project.wasm_opt().unwrap_or_default();
+            if let Some(level) = matches.value_of("wasm-opt") {
+                wasm_opt.level = Some(level.to_string());
+            }
+            if matches.is_present("wasm-opt-strip") {
+                wasm_opt.strip = Some(true);
+            }
+            project.set_wasm_opt(wasm_opt);
+        }
+
+        let options = commands::BuildOptions {
+            analyze: matches.is_present("analyze"),
+            force: matches.is_present("force-build"),
+        };
+
+        if matches.is_present("watch") {
+            commands::build::watch(&project, &options)?;
+        } else {
+            commands::build(&project, &options)?;
+        }
     } else if let Some(matches) = matches.subcommand_matches("preview") {
         info!("Getting project settings");
         let project = settings::project::Project::new()?;
 
-        let method = HTTPMethod::from_str(matches.value_of("method").unwrap_or("get"));
+        let request = preview_request(matches)?;
 
-        let body = match matches.value_of("body") {
-            Some(s) => Some(s.to_string()),
-            None => None,
+        let options = commands::PreviewOptions {
+            verbose: matches.is_present("verbose"),
+            json: matches.is_present("json"),
+            headless: matches.is_present("headless")
+                || settings::preferences::Preferences::new().preview_headless,
+            watch: matches.is_present("watch"),
+            compare: matches
+                .value_of("compare")
+                .map(|url| commands::CompareOptions {
+                    url: url.to_string(),
+                    paths: matches.value_of("paths").map(|p| p.to_string()),
+                    ignore_headers: matches
+                        .values_of("ignore-header")
+                        .map(|h| h.map(|h| h.to_string()).collect())
+                        .unwrap_or_default(),
+                }),
+            replay: matches.value_of("har").map(|har| commands::ReplayOptions {
+                har: har.to_string(),
+                save_responses: matches.value_of("save-responses").map(|d| d.to_string()),
+            }),
+        };
+        let build = commands::BuildOptions {
+            force: matches.is_present("force-build"),
+            ..commands::BuildOptions::default()
         };
 
-        commands::preview(&project, method, body)?;
+        commands::preview(&project, request, &options, &build)?;
+    } else if let Some(matches) = matches.subcommand_matches("dev") {
+        info!("Getting project settings");
+        let project = settings::project::Project::new()?;
+
+        let port = matches.value_of("port").unwrap_or_default();
+        let options = commands::DevOptions {
+            host: matches.value_of("host").unwrap_or_default().to_string(),
+            port: match port.parse() {
+                Ok(port) => port,
+                Err(_) => failure::bail!("Invalid port `{}`", port),
+            },
+            local: matches.is_present("local"),
+            replay: matches.value_of("har").map(|har| commands::ReplayOptions {
+                har: har.to_string(),
+                save_responses: matches.value_of("save-responses").map(|d| d.to_string()),
+            }),
+        };
+
+        commands::dev(&project, &options)?;
+    } else if let Some(matches) = matches.subcommand_matches("bench") {
+        info!("Getting project settings");
+        let project = settings::project::Project::new()?;
+
+        let request = preview_request(matches)?;
+        let count = |name| {
+            let value = matches.value_of(name).unwrap_or_default();
+            match value.parse() {
+                Ok(count) => Ok(count),
+                Err(_) => Err(failure::format_err!("Invalid {} `{}`", name, value)),
+            }
+        };
+        let options = commands::BenchOptions {
+            requests: count("requests")?,
+            concurrency: count("concurrency")?,
+            deployed: matches.is_present("deployed"),
+            json: matches.is_present("json"),
+        };
+        let build = commands::BuildOptions {
+            force: matches.is_present("force-build"),
+            ..commands::BuildOptions::default()
+        };
+
+        commands::bench(&project, request, &options, &build)?;
+    } else if let Some(matches) = matches.subcommand_matches("test") {
+        info!("Getting project settings");
+        let project = settings::project::Project::new()?;
+
+        let options = commands::TestOptions {
+            url: matches.value_of("url").map(|u| u.to_string()),
+            format: matches.value_of("format").unwrap_or("text").parse()?,
+            update_snapshots: matches.is_present("update-snapshots"),
+        };
+
+        let suite = Path::new(matches.value_of("suite").unwrap_or_default());
+        commands::test(&project, suite, &options)?;
     } else if matches.subcommand_matches("whoami").is_some() {
         info!("Getting User settings");
         let user = settings::global_user::GlobalUser::new()?;
@@ -227,7 +631,14 @@ fn main() -> Result<(), failure::Error> {
             _ => false,
         };
 
-        commands::publish(&user, &project, release)?;
+        let force = matches.is_present("force");
+
+        let build = commands::BuildOptions {
+            force: matches.is_present("force-build"),
+            ..commands::BuildOptions::default()
+        };
+
+        commands::publish(&user, &project, release, force, &build)?;
     } else if let Some(matches) = matches.subcommand_matches("subdomain") {
         info!("Getting project settings");
         let project = settings::project::Project::new()?;
@@ -243,3 +654,65 @@ fn main() -> Result<(), failure::Error> {
     }
     Ok(())
 }
+
+// The arguments that describe a request to a worker, as `preview` and `bench`
+// send them.
+fn request_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    vec![
+        Arg::with_name("method")
+            .help("Type of request to send to your worker (get, post, put, patch, delete, head, options)")
+            .index(1),
+        Arg::with_name("body")
+            .help("Body string to send with the request, or - to read it from stdin")
+            .index(2),
+        Arg::with_name("body-option")
+            .long("body")
+            .value_name("BODY")
+            .takes_value(true)
+            .conflicts_with("body")
+            .help("Body string to send with the request, or - to read it from stdin"),
+        Arg::with_name("body-file")
+            .long("body-file")
+            .value_name("FILE")
+            .takes_value(true)
+            .conflicts_with_all(&["body", "body-option"])
+            .help("File to send as the body of the request, or - for stdin"),
+        Arg::with_name("url")
+            .long("url")
+            .takes_value(true)
+            .help("URL your worker sees the request for, or only its path and query (defaults to https://example.com/)"),
+        Arg::with_name("header")
+            .long("header")
+            .short("H")
+            .value_name("NAME: VALUE")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+            .help("Header to send with the request; can be repeated"),
+        Arg::with_name("http")
+            .long("http")
+            .takes_value(false)
+            .help("Make the request over plain HTTP rather than HTTPS"),
+    ]
+}
+
+// The request described by the `request_args` in `matches`.
+fn preview_request(matches: &ArgMatches) -> Result<commands::PreviewRequest, failure::Error> {
+    let method = HTTPMethod::from_str(matches.value_of("method").unwrap_or("get"))?;
+
+    let body = commands::read_body(
+        matches.value_of("body").or(matches.value_of("body-option")),
+        matches.value_of("body-file"),
+    )?;
+    let headers: Vec<&str> = matches
+        .values_of("header")
+        .map(|h| h.collect())
+        .unwrap_or_default();
+    commands::PreviewRequest::new(
+        method,
+        matches.value_of("url"),
+        matches.is_present("http"),
+        &headers,
+        body,
+    )
+}
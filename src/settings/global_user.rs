@@ -1,3 +1,5 @@
+use std::path::PathBuf;
+
 use crate::terminal::emoji;
 
 use config::{Config, Environment, File};
@@ -15,12 +17,17 @@ impl GlobalUser {
     }
 }
 
+// The global config file, without its extension.
+pub fn config_path() -> PathBuf {
+    dirs::home_dir()
+        .expect("oops no home dir")
+        .join(".wrangler/config/default")
+}
+
 fn get_global_config() -> Result<GlobalUser, failure::Error> {
     let mut s = Config::new();
 
-    let config_path = dirs::home_dir()
-        .expect("oops no home dir")
-        .join(".wrangler/config/default");
+    let config_path = config_path();
     let config_str = config_path
         .to_str()
         .expect("global config path should be a string");
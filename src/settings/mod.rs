@@ -1,4 +1,5 @@
 pub mod binding;
 pub mod global_user;
 pub mod metadata;
+pub mod preferences;
 pub mod project;
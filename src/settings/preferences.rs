@@ -0,0 +1,42 @@
+use config::{Config, Environment, File};
+use log::info;
+use serde::Deserialize;
+
+use crate::settings::global_user;
+
+// Global defaults for how `wrangler` behaves, read from the same global
+// config file as the user. Unlike the user, they are all optional, so a
+// missing or broken config file only means the defaults are used.
+#[derive(Debug, Default, Deserialize)]
+pub struct Preferences {
+    // Never open a browser on `preview`
+    #[serde(default)]
+    pub preview_headless: bool,
+}
+
+impl Preferences {
+    pub fn new() -> Preferences {
+        match get_preferences() {
+            Ok(preferences) => preferences,
+            Err(e) => {
+                info!("using default preferences: {}", e);
+                Preferences::default()
+            }
+        }
+    }
+}
+
+fn get_preferences() -> Result<Preferences, failure::Error> {
+    let mut s = Config::new();
+
+    let config_path = global_user::config_path();
+    let config_str = config_path
+        .to_str()
+        .expect("global config path should be a string");
+    s.merge(File::with_name(config_str).required(false))?;
+
+    // Eg.. `CF_PREVIEW_HEADLESS=true` would set the `preview_headless` key
+    s.merge(Environment::with_prefix("CF"))?;
+
+    Ok(s.try_into()?)
+}
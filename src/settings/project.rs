@@ -1,6 +1,6 @@
 use crate::terminal::emoji;
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fmt;
 use std::fs;
 use std::path::{Path, PathBuf};
@@ -11,6 +11,9 @@ use log::info;
 use config::{Config, Environment, File};
 use serde::{Deserialize, Serialize};
 
+// The size limit of a Worker on the free plan, after compression.
+const DEFAULT_SIZE_LIMIT: u64 = 1 << 20; // 1 MiB
+
 #[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Project {
     pub name: String,
@@ -24,6 +27,87 @@ pub struct Project {
     pub routes: Option<HashMap<String, String>>,
     #[serde(rename = "kv-namespaces")]
     pub kv_namespaces: Option<Vec<String>>,
+    pub deploy_state: Option<String>,
+    pub plan: Option<String>,
+    pub size_limits: Option<HashMap<String, u64>>,
+    pub build: Option<Build>,
+}
+
+// How the project is built, per project type, from the `[build]` section.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct Build {
+    pub rust: Option<RustBuild>,
+    pub wasm_opt: Option<WasmOpt>,
+    pub webpack: Option<WebpackBuild>,
+}
+
+// How wasm-pack builds a Rust project, from `[build.rust]`.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct RustBuild {
+    // The package to build, in a Cargo workspace of several crates
+    #[serde(rename = "crate")]
+    pub krate: Option<String>,
+    // The directory of the crate to build, relative to the project
+    pub path: Option<String>,
+    // `dev`, `release` or `profiling`; wasm-pack builds for release by default
+    pub profile: Option<String>,
+    // Cargo features to enable
+    pub features: Option<Vec<String>>,
+    // More arguments for `wasm-pack build`
+    pub wasm_pack_args: Option<Vec<String>>,
+    // Where wasm-pack, or wasm-bindgen, writes its output, relative to the project
+    pub out_dir: Option<String>,
+    // `wasm-pack`, or `cargo` to build with cargo and wasm-bindgen; wasm-pack
+    // is used unless it cannot be installed
+    pub backend: Option<String>,
+}
+
+// Where wasm-pack writes its output unless `out_dir` says otherwise
+const DEFAULT_RUST_OUT_DIR: &str = "pkg";
+
+impl RustBuild {
+    pub fn out_dir(&self) -> String {
+        self.out_dir
+            .clone()
+            .unwrap_or_else(|| DEFAULT_RUST_OUT_DIR.to_string())
+    }
+}
+
+// How wranglerjs builds a webpack project, from `[build.webpack]`.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct WebpackBuild {
+    // The configuration to build when the webpack config exports several, by
+    // its `name` or its index
+    pub config: Option<String>,
+    // `development`, `production` or `none`; the config decides by default
+    pub mode: Option<String>,
+    // What a config that is a function is called with as its `env`
+    pub env: Option<BTreeMap<String, serde_json::Value>>,
+}
+
+// How wasm-opt optimises the Wasm of a build, from `[build.wasm_opt]`. The
+// Wasm is only optimised if the section is there.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct WasmOpt {
+    // The optimisation level: 0 to 4, or `s` and `z` to optimise for size
+    pub level: Option<String>,
+    // Strip the name section and the other custom sections from the Wasm
+    pub strip: Option<bool>,
+}
+
+// What wasm-opt optimises for unless `level` says otherwise
+const DEFAULT_WASM_OPT_LEVEL: &str = "s";
+
+impl WasmOpt {
+    pub fn level(&self) -> String {
+        self.level
+            .clone()
+            .unwrap_or_else(|| DEFAULT_WASM_OPT_LEVEL.to_string())
+    }
+
+    pub fn strip(&self) -> bool {
+        self.strip.unwrap_or(false)
+    }
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -80,6 +164,10 @@ impl Project {
             routes: None,
             kv_namespaces: None,
             webpack_config: None,
+            deploy_state: None,
+            plan: None,
+            size_limits: None,
+            build: None,
         };
 
         let toml = toml::to_string(&project)?;
@@ -98,6 +186,56 @@ impl Project {
     pub fn new() -> Result<Self, failure::Error> {
         get_project_config()
     }
+
+    // The maximum compressed size of the upload, in bytes, for the `plan`
+    // of this project as set in the `size_limits` table.
+    pub fn size_limit(&self) -> u64 {
+        let plan = self.plan.clone().unwrap_or_else(|| "free".to_string());
+        self.size_limits
+            .as_ref()
+            .and_then(|limits| limits.get(&plan))
+            .cloned()
+            .unwrap_or(DEFAULT_SIZE_LIMIT)
+    }
+
+    // The `[build.rust]` section, or its defaults.
+    pub fn rust_build(&self) -> RustBuild {
+        self.build
+            .as_ref()
+            .and_then(|build| build.rust.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn set_rust_build(&mut self, rust: RustBuild) {
+        let mut build = self.build.clone().unwrap_or_default();
+        build.rust = Some(rust);
+        self.build = Some(build);
+    }
+
+    // The `[build.webpack]` section, or its defaults.
+    pub fn webpack_build(&self) -> WebpackBuild {
+        self.build
+            .as_ref()
+            .and_then(|build| build.webpack.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn set_webpack_build(&mut self, webpack: WebpackBuild) {
+        let mut build = self.build.clone().unwrap_or_default();
+        build.webpack = Some(webpack);
+        self.build = Some(build);
+    }
+
+    // The `[build.wasm_opt]` section, if the Wasm is to be optimised.
+    pub fn wasm_opt(&self) -> Option<WasmOpt> {
+        self.build.as_ref().and_then(|build| build.wasm_opt.clone())
+    }
+
+    pub fn set_wasm_opt(&mut self, wasm_opt: WasmOpt) {
+        let mut build = self.build.clone().unwrap_or_default();
+        build.wasm_opt = Some(wasm_opt);
+        self.build = Some(build);
+    }
 }
 
 pub fn get_project_config() -> Result<Project, failure::Error> {
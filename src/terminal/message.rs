@@ -1,8 +1,26 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
 use super::emoji;
 use console::Emoji;
 
+// Whether messages go to stderr, leaving stdout to machine-readable output.
+static TO_STDERR: AtomicBool = AtomicBool::new(false);
+
+pub fn to_stderr(enabled: bool) {
+    TO_STDERR.store(enabled, Ordering::Relaxed);
+}
+
+// Prints a line without any decoration, such as a line of a report.
+pub fn detail(msg: &str) {
+    if TO_STDERR.load(Ordering::Relaxed) {
+        eprintln!("{}", msg);
+    } else {
+        println!("{}", msg);
+    }
+}
+
 fn message(label: Emoji, msg: &str) {
-    println!("{0} {1} {0}", label, msg);
+    detail(&format!("{0} {1} {0}", label, msg));
 }
 
 pub fn info(msg: &str) {
@@ -13,6 +31,10 @@ pub fn success(msg: &str) {
     message(emoji::SPARKLES, msg);
 }
 
+pub fn warn(msg: &str) {
+    message(emoji::WARN, msg);
+}
+
 pub fn user_error(msg: &str) {
     message(emoji::EYES, msg);
 }
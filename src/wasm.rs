@@ -0,0 +1,289 @@
+//! Inspection of the WebAssembly modules that `wrangler` uploads.
+//!
+//! This only reads what `wrangler` needs to report on a module: its imports
+//! and exports, the size of its sections and the size of each function body,
+//! named after the `name` custom section when present.
+
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+use wasmparser::{
+    ExternalKind, ImportSectionEntryType, Name, NameSectionReader, OperatorValidatorConfig, Parser,
+    ParserState, SectionCode, ValidatingParser, ValidatingParserConfig, WasmDecoder,
+};
+
+#[derive(Debug)]
+pub struct WasmModule {
+    pub size: u64,
+    pub sections: Vec<WasmSection>,
+    pub functions: Vec<WasmFunction>,
+    pub imports: Vec<WasmImport>,
+    pub exports: Vec<WasmExport>,
+    // Whether the module defines or imports a shared memory, which requires
+    // threads
+    pub shared_memory: bool,
+}
+
+#[derive(Debug)]
+pub struct WasmImport {
+    pub module: String,
+    pub field: String,
+    pub kind: &'static str,
+}
+
+#[derive(Debug)]
+pub struct WasmExport {
+    pub field: String,
+    pub kind: &'static str,
+}
+
+#[derive(Debug)]
+pub struct WasmSection {
+    pub name: String,
+    pub size: u64,
+}
+
+#[derive(Debug)]
+pub struct WasmFunction {
+    pub name: String,
+    pub size: u64,
+}
+
+impl WasmModule {
+    // Reads the module, checking only its structure.
+    pub fn parse(bytes: &[u8]) -> Result<WasmModule, failure::Error> {
+        read(&mut Parser::new(bytes), bytes.len() as u64)
+    }
+
+    // Reads the module and fully validates it in the same pass, the way
+    // `WebAssembly.validate` would. Proposals are allowed, so that modules
+    // using them are reported by the caller rather than rejected here.
+    pub fn parse_valid(bytes: &[u8]) -> Result<WasmModule, failure::Error> {
+        let config = ValidatingParserConfig {
+            operator_config: OperatorValidatorConfig {
+                enable_threads: true,
+                enable_reference_types: true,
+                enable_simd: true,
+                enable_bulk_memory: true,
+                enable_multi_value: true,
+            },
+            mutable_global_imports: true,
+        };
+        read(
+            &mut ValidatingParser::new(bytes, Some(config)),
+            bytes.len() as u64,
+        )
+    }
+
+    // The `n` largest function bodies, largest first.
+    pub fn largest_functions(&self, n: usize) -> Vec<&WasmFunction> {
+        let mut functions: Vec<&WasmFunction> = self.functions.iter().collect();
+        functions.sort_by_key(|f| Reverse(f.size));
+        functions.truncate(n);
+        functions
+    }
+}
+
+// Reads a module of `size` bytes from the events of `decoder`.
+fn read<'a, D: WasmDecoder<'a>>(decoder: &mut D, size: u64) -> Result<WasmModule, failure::Error> {
+    let mut sections = vec![];
+    let mut imported_functions = 0;
+    let mut bodies = vec![];
+    let mut imports = vec![];
+    let mut exports = vec![];
+    let mut shared_memory = false;
+    // the name section is read once the module is, so that a malformed one
+    // only costs us the names
+    let mut in_names = false;
+    let mut names_section = vec![];
+
+    loop {
+        match decoder.read() {
+            ParserState::EndWasm => break,
+            ParserState::Error(e) => failure::bail!(
+                "invalid WebAssembly module at offset {}: {}",
+                e.offset,
+                e.message
+            ),
+            ParserState::BeginSection { code, range } => {
+                in_names = match code {
+                    SectionCode::Custom { name, .. } => *name == "name",
+                    _ => false,
+                };
+                sections.push(WasmSection {
+                    name: section_name(code),
+                    size: (range.end - range.start) as u64,
+                });
+            }
+            ParserState::SectionRawData(data) if in_names => names_section.extend_from_slice(data),
+            ParserState::ImportSectionEntry { module, field, ty } => {
+                let kind = match ty {
+                    ImportSectionEntryType::Function(_) => {
+                        imported_functions += 1;
+                        "function"
+                    }
+                    ImportSectionEntryType::Table(_) => "table",
+                    ImportSectionEntryType::Memory(memory) => {
+                        shared_memory |= memory.shared;
+                        "memory"
+                    }
+                    ImportSectionEntryType::Global(_) => "global",
+                };
+                imports.push(WasmImport {
+                    module: module.to_string(),
+                    field: field.to_string(),
+                    kind,
+                });
+            }
+            ParserState::MemorySectionEntry(memory) => shared_memory |= memory.shared,
+            ParserState::ExportSectionEntry { field, kind, .. } => exports.push(WasmExport {
+                field: field.to_string(),
+                kind: external_kind(*kind),
+            }),
+            ParserState::BeginFunctionBody { range } => {
+                bodies.push((range.end - range.start) as u64)
+            }
+            _ => {}
+        }
+    }
+
+    let mut names = function_names(&names_section);
+    let functions = bodies
+        .into_iter()
+        .enumerate()
+        .map(|(i, size)| {
+            let index = imported_functions + i as u32;
+            WasmFunction {
+                name: names
+                    .remove(&index)
+                    .unwrap_or_else(|| format!("function[{}]", index)),
+                size,
+            }
+        })
+        .collect();
+
+    Ok(WasmModule {
+        size,
+        sections,
+        functions,
+        imports,
+        exports,
+        shared_memory,
+    })
+}
+
+// The names of functions in the name section `data`, as far as it can be read
+fn function_names(data: &[u8]) -> HashMap<u32, String> {
+    let mut names = HashMap::new();
+    if let Ok(reader) = NameSectionReader::new(data, 0) {
+        for name in reader {
+            if let Ok(Name::Function(functions)) = name {
+                if let Ok(mut map) = functions.get_map() {
+                    for _ in 0..map.get_count() {
+                        if let Ok(naming) = map.read() {
+                            names.insert(naming.index, naming.name.to_string());
+                        }
+                    }
+                }
+            }
+        }
+    }
+    names
+}
+
+fn external_kind(kind: ExternalKind) -> &'static str {
+    match kind {
+        ExternalKind::Function => "function",
+        ExternalKind::Table => "table",
+        ExternalKind::Memory => "memory",
+        ExternalKind::Global => "global",
+    }
+}
+
+fn section_name(code: &SectionCode) -> String {
+    match code {
+        SectionCode::Custom { name, .. } => format!("custom:{}", name),
+        SectionCode::Type => "type".to_string(),
+        SectionCode::Import => "import".to_string(),
+        SectionCode::Function => "function".to_string(),
+        SectionCode::Table => "table".to_string(),
+        SectionCode::Memory => "memory".to_string(),
+        SectionCode::Global => "global".to_string(),
+        SectionCode::Export => "export".to_string(),
+        SectionCode::Start => "start".to_string(),
+        SectionCode::Element => "element".to_string(),
+        SectionCode::Code => "code".to_string(),
+        SectionCode::Data => "data".to_string(),
+        SectionCode::DataCount => "datacount".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // (module
+    //   (import "env" "log" (func))
+    //   (func $answer (result i32) i32.const 42)
+    //   (export "answer" (func $answer)))
+    // with a name section naming function 1 "answer"
+    #[rustfmt::skip]
+    const MODULE: &[u8] = &[
+        0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00, // header
+        0x01, 0x08, 0x02, 0x60, 0x00, 0x00, 0x60, 0x00, 0x01, 0x7f, // type
+        0x02, 0x0b, 0x01, 0x03, 0x65, 0x6e, 0x76, 0x03, 0x6c, 0x6f, 0x67, 0x00, 0x00, // import
+        0x03, 0x02, 0x01, 0x01, // function
+        0x07, 0x0a, 0x01, 0x06, 0x61, 0x6e, 0x73, 0x77, 0x65, 0x72, 0x00, 0x01, // export
+        0x0a, 0x06, 0x01, 0x04, 0x00, 0x41, 0x2a, 0x0b, // code
+        0x00, 0x10, 0x04, 0x6e, 0x61, 0x6d, 0x65, 0x01, 0x09, 0x01, 0x01, 0x06, 0x61, 0x6e, 0x73,
+        0x77, 0x65, 0x72, // name
+    ];
+
+    #[test]
+    fn it_reads_sections_and_functions() {
+        let module = WasmModule::parse(MODULE).unwrap();
+
+        let sections: Vec<&str> = module.sections.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(
+            sections,
+            vec![
+                "type",
+                "import",
+                "function",
+                "export",
+                "code",
+                "custom:name"
+            ]
+        );
+        assert_eq!(module.functions.len(), 1);
+        assert_eq!(module.functions[0].name, "answer");
+        assert_eq!(module.functions[0].size, 4);
+    }
+
+    #[test]
+    fn it_reads_imports_and_exports() {
+        let module = WasmModule::parse(MODULE).unwrap();
+
+        assert_eq!(module.imports.len(), 1);
+        assert_eq!(module.imports[0].module, "env");
+        assert_eq!(module.imports[0].field, "log");
+        assert_eq!(module.imports[0].kind, "function");
+        assert_eq!(module.exports.len(), 1);
+        assert_eq!(module.exports[0].field, "answer");
+        assert!(!module.shared_memory);
+        assert!(WasmModule::parse_valid(MODULE).is_ok());
+    }
+
+    #[test]
+    fn it_rejects_malformed_modules() {
+        assert!(WasmModule::parse(b"abc").is_err());
+        assert!(WasmModule::parse(&MODULE[..20]).is_err());
+        assert!(WasmModule::parse_valid(&MODULE[..20]).is_err());
+
+        // a function that leaves an i32 behind, but is typed to return nothing
+        let mut module = MODULE.to_vec();
+        module[34] = 0x00;
+        assert!(WasmModule::parse(&module).is_ok());
+        assert!(WasmModule::parse_valid(&module).is_err());
+    }
+}
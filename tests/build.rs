@@ -115,6 +115,76 @@ fn it_fails_with_multiple_specify_webpack_configs() {
     cleanup(fixture);
 }
 
+#[test]
+fn it_builds_the_selected_webpack_config() {
+    let fixture = "webpack_select_config";
+    create_temporary_copy(fixture);
+
+    settings! {fixture, r#"
+        type = "Webpack"
+
+        [build.webpack]
+        config = "b"
+    "#};
+    build(fixture);
+    assert!(script(fixture).contains("from b"));
+
+    settings! {fixture, r#"
+        type = "Webpack"
+
+        [build.webpack]
+        config = "0"
+    "#};
+    build(fixture);
+    assert!(script(fixture).contains("from a"));
+
+    settings! {fixture, r#"
+        type = "Webpack"
+
+        [build.webpack]
+        config = "c"
+    "#};
+    build_fails_with(
+        fixture,
+        "Your webpack configuration has no configuration named or numbered `c`; it has 0 (a), 1 (b).",
+    );
+    cleanup(fixture);
+}
+
+#[test]
+fn it_builds_with_webpack_function_config() {
+    let fixture = "webpack_function_config";
+    create_temporary_copy(fixture);
+
+    settings! {fixture, r#"
+        type = "Webpack"
+
+        [build.webpack]
+        mode = "development"
+
+        [build.webpack.env]
+        greeting = "hello"
+    "#};
+
+    build(fixture);
+    assert!(script(fixture).contains("hello in development"));
+    cleanup(fixture);
+}
+
+#[test]
+fn it_builds_with_webpack_promise_config() {
+    let fixture = "webpack_promise_config";
+    create_temporary_copy(fixture);
+
+    settings! {fixture, r#"
+        type = "Webpack"
+    "#};
+
+    build(fixture);
+    assert!(fixture_out_path(fixture).join("script.js").exists());
+    cleanup(fixture);
+}
+
 #[test]
 fn it_builds_with_webpack_wast() {
     let fixture = "webpack_wast";
@@ -176,6 +246,10 @@ fn build_fails_with(fixture: &str, expected_message: &str) {
     );
 }
 
+fn script(fixture: &str) -> String {
+    fs::read_to_string(fixture_out_path(fixture).join("script.js")).expect("could not read script")
+}
+
 fn fixture_path(fixture: &str) -> PathBuf {
     let mut dest = env::temp_dir();
     dest.push(fixture);
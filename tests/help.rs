@@ -19,3 +19,9 @@ fn it_fails_on_bad_command() {
     let mut wrangler = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
     wrangler.arg("farts").assert().failure();
 }
+
+#[test]
+fn it_requires_a_url_for_deployed_benches() {
+    let mut wrangler = Command::cargo_bin(env!("CARGO_PKG_NAME")).unwrap();
+    wrangler.args(["bench", "--deployed"]).assert().failure();
+}
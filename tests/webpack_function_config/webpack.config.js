@@ -0,0 +1,3 @@
+module.exports = (env, argv) => ({
+  entry: `./${env.greeting}-${argv.mode}.js`
+})
@@ -0,0 +1 @@
+// js
@@ -0,0 +1 @@
+module.exports = () => Promise.resolve({ entry: "./index.js" })
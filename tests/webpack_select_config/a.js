@@ -0,0 +1 @@
+addEventListener("fetch", event => event.respondWith(new Response("from a")));
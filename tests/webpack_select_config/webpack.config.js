@@ -0,0 +1,4 @@
+module.exports = [
+  { name: "a", entry: "./a.js" },
+  { name: "b", entry: "./b.js" }
+]
@@ -1,6 +1,6 @@
 const webpack = require("webpack");
 const { join } = require("path");
-const { writeFileSync } = require("fs");
+const { renameSync, writeFileSync } = require("fs");
 const WasmMainTemplatePlugin = require("webpack/lib/wasm/WasmMainTemplatePlugin");
 
 function error(msg) {
@@ -15,7 +15,10 @@ const args = rawArgs.reduce((obj, e) => {
     throw error("malformed arguments");
   }
 
-  const [name, value] = e.split("=");
+  // values, such as the JSON of --env, may hold `=` themselves
+  const separator = e.indexOf("=");
+  const name = separator === -1 ? e : e.slice(0, separator);
+  const value = separator === -1 ? undefined : e.slice(separator + 1);
   const normalizedName = name.replace("--", "");
   obj[normalizedName] = value;
   return obj;
@@ -28,48 +31,68 @@ if (args["no-webpack-config"] === "1") {
   config = require(join(process.cwd(), args["webpack-config"]));
 }
 
-if (Array.isArray(config)) {
+// A config can be a function of the env and of the arguments, like with
+// webpack-cli, and so can each of several configs. Any of them can return a
+// Promise of the config, which is waited for.
+const mode = args["mode"];
+const env = args["env"] ? JSON.parse(args["env"]) : {};
+const resolve = c => Promise.resolve(typeof c === "function" ? c(env, { mode }) : c);
+
+resolve(config)
+  .then(config =>
+    Array.isArray(config)
+      ? Promise.all(config.map(resolve)).then(configs =>
+          selectConfig(configs, args["use-config"])
+        )
+      : config
+  )
+  .then(config => {
+    if (mode) {
+      config.mode = mode;
+    }
+    build(config);
+  })
+  .catch(err => {
+    throw error(String(err.stack || err));
+  });
+
+// Picks the configuration to build out of the several a config exports, by
+// its `name` or its index, as set in the `[build.webpack]` section of
+// wrangler.toml.
+function selectConfig(configs, selected) {
+  if (selected === undefined) {
+    throw error(
+      "Multiple webpack configurations are not supported. You can specify a different path for your webpack configuration file in wrangler.toml with the `webpack_config` field\n" +
+        "Please make sure that your webpack configuration exports an Object, or choose one of its configurations by `name` or index with the `config` field of the `[build.webpack]` section in wrangler.toml."
+    );
+  }
+
+  const byName = configs.find(c => c.name === selected);
+  if (byName) {
+    return byName;
+  }
+  const index = Number(selected);
+  if (Number.isInteger(index) && index >= 0 && index < configs.length) {
+    return configs[index];
+  }
+
+  const available = configs
+    .map((c, i) => (c.name ? `${i} (${c.name})` : `${i}`))
+    .join(", ");
   throw error(
-    "Multiple webpack configurations are not supported. You can specify a different path for your webpack configuration file in wrangler.toml with the `webpack_config` field\n" +
-      "Please make sure that your webpack configuration exports an Object."
+    `Your webpack configuration has no configuration named or numbered \`${selected}\`; it has ${available}.`
   );
 }
 
-const compiler = webpack(config);
-const fullConfig = compiler.options;
-
 function filterByExtension(ext) {
   return v => v.indexOf("." + ext) !== -1;
 }
 
-// Override the {FetchCompileWasmTemplatePlugin} and inject our new runtime.
-const [
-  fetchCompileWasmTemplatePlugin
-] = compiler.hooks.thisCompilation.taps.filter(
-  tap => tap.name === "FetchCompileWasmTemplatePlugin"
-);
-fetchCompileWasmTemplatePlugin.fn = function(compilation) {
-  const mainTemplate = compilation.mainTemplate;
-  const generateLoadBinaryCode = () => `
-      // Fake fetch response
-      Promise.resolve({
-        arrayBuffer() { return Promise.resolve(${args["wasm-binding"]}); }
-      });
-    `;
-
-  const plugin = new WasmMainTemplatePlugin({
-    generateLoadBinaryCode,
-    mangleImports: false,
-    supportsStreaming: false
-  });
-  plugin.apply(mainTemplate);
-};
-
-compiler.run((err, stats) => {
-  if (err) {
-    throw err;
-  }
+// Printed on stdout each time a build in watch mode has been written to the
+// output file.
+const BUILD_MARKER = "[wranglerjs] build written";
 
+function toBundle(stats) {
   const assets = stats.compilation.assets;
   const jsonStats = stats.toJson();
   const bundle = {
@@ -91,5 +114,63 @@ compiler.run((err, stats) => {
     bundle.wasm = Buffer.from(assets[wasmModuleAsset].source()).toString("base64");
   }
 
-  writeFileSync(args["output-file"], JSON.stringify(bundle));
-});
+  if (args["analyze"] === "1") {
+    bundle.modules = jsonStats.modules.map(m => ({ name: m.name, size: m.size }));
+  }
+
+  return bundle;
+}
+
+// Builds the resolved config, once or, in watch mode, on every change.
+function build(config) {
+  const compiler = webpack(config);
+
+  // Override the {FetchCompileWasmTemplatePlugin} and inject our new runtime.
+  const [
+    fetchCompileWasmTemplatePlugin
+  ] = compiler.hooks.thisCompilation.taps.filter(
+    tap => tap.name === "FetchCompileWasmTemplatePlugin"
+  );
+  fetchCompileWasmTemplatePlugin.fn = function(compilation) {
+    const mainTemplate = compilation.mainTemplate;
+    const generateLoadBinaryCode = () => `
+        // Fake fetch response
+        Promise.resolve({
+          arrayBuffer() { return Promise.resolve(${args["wasm-binding"]}); }
+        });
+      `;
+
+    const plugin = new WasmMainTemplatePlugin({
+      generateLoadBinaryCode,
+      mangleImports: false,
+      supportsStreaming: false
+    });
+    plugin.apply(mainTemplate);
+  };
+
+  if (args["watch"] === "1") {
+    // Keep running and rebuild on changes. Each build replaces the output file
+    // at once, so that it is never read half-written.
+    const watchOptions = {
+      aggregateTimeout: 300,
+      ignored: [/node_modules/, /[\\/]worker[\\/]/, /[\\/]pkg[\\/]/]
+    };
+    compiler.watch(watchOptions, (err, stats) => {
+      const bundle = err
+        ? { wasm: null, script: "", errors: [String(err.stack || err)] }
+        : toBundle(stats);
+      const tmp = args["output-file"] + ".tmp";
+      writeFileSync(tmp, JSON.stringify(bundle));
+      renameSync(tmp, args["output-file"]);
+      console.log(BUILD_MARKER);
+    });
+  } else {
+    compiler.run((err, stats) => {
+      if (err) {
+        throw err;
+      }
+
+      writeFileSync(args["output-file"], JSON.stringify(toBundle(stats)));
+    });
+  }
+}
@@ -0,0 +1,433 @@
+// A Service Worker emulator, running a built worker locally the way the
+// Workers runtime would, as far as it can: `fetch` events, Wasm module
+// bindings and KV namespaces backed by files.
+//
+// It has no dependencies, so that it runs without a network connection.
+const http = require("http");
+const https = require("https");
+const fs = require("fs");
+const path = require("path");
+const vm = require("vm");
+const nodeCrypto = require("crypto");
+const { URL, URLSearchParams } = require("url");
+const { TextEncoder, TextDecoder } = require("util");
+
+function error(msg) {
+  console.error("Error: " + msg);
+  process.exit(1);
+  return new Error("error");
+}
+
+const rawArgs = process.argv.slice(2);
+const args = rawArgs.reduce((obj, e) => {
+  if (e.indexOf("--") === -1 && e.indexOf("=") === -1) {
+    throw error("malformed arguments");
+  }
+
+  const [name, ...value] = e.split("=");
+  const normalizedName = name.replace("--", "");
+  obj[normalizedName] = value.join("=");
+  return obj;
+}, {});
+
+function toBuffer(body) {
+  if (body === undefined || body === null) {
+    return null;
+  }
+  if (Buffer.isBuffer(body)) {
+    return body;
+  }
+  if (body instanceof ArrayBuffer) {
+    return Buffer.from(body);
+  }
+  if (ArrayBuffer.isView(body)) {
+    return Buffer.from(body.buffer, body.byteOffset, body.byteLength);
+  }
+  return Buffer.from(String(body));
+}
+
+// Node gives repeated headers as arrays
+function fromNodeHeaders(nodeHeaders) {
+  const headers = new Headers();
+  Object.keys(nodeHeaders).forEach(name => {
+    [].concat(nodeHeaders[name]).forEach(v => headers.append(name, v));
+  });
+  return headers;
+}
+
+function toNodeHeaders(headers) {
+  const nodeHeaders = {};
+  headers._map.forEach((values, name) => {
+    nodeHeaders[name] = name === "set-cookie" ? values : values.join(", ");
+  });
+  return nodeHeaders;
+}
+
+class Headers {
+  constructor(init) {
+    this._map = new Map();
+    if (init instanceof Headers) {
+      init._map.forEach((values, name) => this._map.set(name, values.slice()));
+    } else if (Array.isArray(init)) {
+      init.forEach(([name, value]) => this.append(name, value));
+    } else if (init) {
+      Object.keys(init).forEach(name => this.append(name, init[name]));
+    }
+  }
+
+  append(name, value) {
+    const key = String(name).toLowerCase();
+    const values = this._map.get(key) || [];
+    values.push(String(value));
+    this._map.set(key, values);
+  }
+
+  set(name, value) {
+    this._map.set(String(name).toLowerCase(), [String(value)]);
+  }
+
+  get(name) {
+    const values = this._map.get(String(name).toLowerCase());
+    return values ? values.join(", ") : null;
+  }
+
+  has(name) {
+    return this._map.has(String(name).toLowerCase());
+  }
+
+  delete(name) {
+    this._map.delete(String(name).toLowerCase());
+  }
+
+  forEach(callback, thisArg) {
+    this._map.forEach((values, name) =>
+      callback.call(thisArg, values.join(", "), name, this)
+    );
+  }
+
+  *entries() {
+    for (const [name, values] of this._map) {
+      yield [name, values.join(", ")];
+    }
+  }
+
+  *keys() {
+    yield* this._map.keys();
+  }
+
+  *values() {
+    for (const [, value] of this.entries()) {
+      yield value;
+    }
+  }
+
+  [Symbol.iterator]() {
+    return this.entries();
+  }
+}
+
+class Body {
+  constructor(body) {
+    this._body = toBuffer(body);
+    this.bodyUsed = false;
+  }
+
+  _consume() {
+    if (this.bodyUsed) {
+      return Promise.reject(new TypeError("Body has already been used"));
+    }
+    this.bodyUsed = true;
+    return Promise.resolve(this._body || Buffer.alloc(0));
+  }
+
+  text() {
+    return this._consume().then(b => b.toString("utf8"));
+  }
+
+  json() {
+    return this.text().then(JSON.parse);
+  }
+
+  arrayBuffer() {
+    return this._consume().then(b =>
+      b.buffer.slice(b.byteOffset, b.byteOffset + b.byteLength)
+    );
+  }
+}
+
+class Request extends Body {
+  constructor(input, init = {}) {
+    const source = input instanceof Request ? input : null;
+    super(init.body !== undefined ? init.body : source ? source._body : null);
+    this.url = source ? source.url : new URL(String(input)).toString();
+    this.method = (init.method || (source && source.method) || "GET").toUpperCase();
+    this.headers = new Headers(init.headers || (source && source.headers));
+    this.redirect = init.redirect || (source && source.redirect) || "follow";
+    this.cf = (source && source.cf) || {};
+  }
+
+  clone() {
+    return new Request(this);
+  }
+}
+
+class Response extends Body {
+  constructor(body, init = {}) {
+    super(body);
+    this.status = init.status === undefined ? 200 : init.status;
+    this.statusText = init.statusText || http.STATUS_CODES[this.status] || "";
+    this.headers = new Headers(init.headers);
+    if (typeof body === "string" && !this.headers.has("content-type")) {
+      this.headers.set("content-type", "text/plain;charset=UTF-8");
+    }
+    this.url = init.url || "";
+    this.redirected = false;
+  }
+
+  get ok() {
+    return this.status >= 200 && this.status < 300;
+  }
+
+  clone() {
+    return new Response(this._body, this);
+  }
+
+  static redirect(url, status = 302) {
+    return new Response(null, { status, headers: { location: String(url) } });
+  }
+}
+
+class FetchEvent {
+  constructor(request) {
+    this.type = "fetch";
+    this.request = request;
+    this._response = null;
+    this._waitUntil = [];
+  }
+
+  respondWith(response) {
+    this._response = Promise.resolve(response);
+  }
+
+  waitUntil(promise) {
+    this._waitUntil.push(Promise.resolve(promise));
+  }
+
+  passThroughOnException() {}
+}
+
+// Outgoing requests, made with Node itself
+function fetch(input, init) {
+  const request = new Request(input, init);
+  return new Promise((resolve, reject) => {
+    const url = new URL(request.url);
+    const client = url.protocol === "https:" ? https : http;
+    const options = {
+      method: request.method,
+      headers: toNodeHeaders(request.headers)
+    };
+
+    const req = client.request(url, options, res => {
+      const chunks = [];
+      res.on("data", chunk => chunks.push(chunk));
+      res.on("end", () => {
+        const location = res.headers["location"];
+        if (res.statusCode >= 300 && res.statusCode < 400 && location && request.redirect === "follow") {
+          const method = res.statusCode === 303 ? "GET" : request.method;
+          const body = method === "GET" ? undefined : request._body;
+          resolve(fetch(new URL(location, url).toString(), { method, headers: request.headers, body }));
+          return;
+        }
+
+        resolve(
+          new Response(Buffer.concat(chunks), {
+            status: res.statusCode,
+            statusText: res.statusMessage,
+            headers: fromNodeHeaders(res.headers),
+            url: request.url
+          })
+        );
+      });
+    });
+    req.on("error", reject);
+    if (request._body) {
+      req.write(request._body);
+    }
+    req.end();
+  });
+}
+
+// A KV namespace stored as one file per key
+class KVNamespace {
+  constructor(dir) {
+    this._dir = dir;
+    fs.mkdirSync(dir, { recursive: true });
+  }
+
+  _path(key) {
+    return path.join(this._dir, encodeURIComponent(key));
+  }
+
+  get(key, type = "text") {
+    return new Promise(resolve => {
+      fs.readFile(this._path(key), (err, data) => {
+        if (err) {
+          return resolve(null);
+        }
+        switch (type) {
+          case "json":
+            return resolve(JSON.parse(data.toString("utf8")));
+          case "arrayBuffer":
+            return resolve(data.buffer.slice(data.byteOffset, data.byteOffset + data.byteLength));
+          default:
+            return resolve(data.toString("utf8"));
+        }
+      });
+    });
+  }
+
+  put(key, value) {
+    return new Promise((resolve, reject) => {
+      fs.writeFile(this._path(key), toBuffer(value) || "", err => (err ? reject(err) : resolve()));
+    });
+  }
+
+  delete(key) {
+    return new Promise((resolve, reject) => {
+      fs.unlink(this._path(key), err => (err && err.code !== "ENOENT" ? reject(err) : resolve()));
+    });
+  }
+
+  list({ prefix = "", limit = 1000 } = {}) {
+    return new Promise((resolve, reject) => {
+      fs.readdir(this._dir, (err, files) => {
+        if (err) {
+          return reject(err);
+        }
+        const keys = files
+          .map(f => decodeURIComponent(f))
+          .filter(name => name.startsWith(prefix))
+          .sort()
+          .slice(0, limit)
+          .map(name => ({ name }));
+        resolve({ keys, list_complete: true, cursor: "" });
+      });
+    });
+  }
+}
+
+const listeners = [];
+const globals = {
+  self: global,
+  addEventListener(type, listener) {
+    if (type === "fetch") {
+      listeners.push(listener);
+    }
+  },
+  fetch,
+  Request,
+  Response,
+  Headers,
+  FetchEvent,
+  URL,
+  URLSearchParams,
+  TextEncoder,
+  TextDecoder,
+  atob: s => Buffer.from(s, "base64").toString("binary"),
+  btoa: s => Buffer.from(s, "binary").toString("base64"),
+  crypto: {
+    getRandomValues(array) {
+      nodeCrypto.randomFillSync(array);
+      return array;
+    }
+  },
+  caches: {
+    default: {
+      match: () => Promise.resolve(undefined),
+      put: () => Promise.resolve(),
+      delete: () => Promise.resolve(false)
+    }
+  }
+};
+
+function define(name, value) {
+  Object.defineProperty(global, name, {
+    value,
+    writable: true,
+    configurable: true,
+    enumerable: false
+  });
+}
+
+// Bindings, as declared in the metadata that would be uploaded
+const bundleDir = args["bundle"];
+const metadata = JSON.parse(fs.readFileSync(path.join(bundleDir, "metadata"), "utf8"));
+const kvDir = args["kv-dir"];
+
+Object.keys(globals).forEach(name => define(name, globals[name]));
+(metadata.bindings || []).forEach(binding => {
+  switch (binding.type) {
+    case "wasm_module":
+      define(binding.name, new WebAssembly.Module(fs.readFileSync(path.join(bundleDir, binding.part))));
+      break;
+    case "kv_namespace":
+      define(binding.name, new KVNamespace(path.join(kvDir, binding.name)));
+      break;
+    default:
+      console.warn(`Binding ${binding.name} of type ${binding.type} is not emulated`);
+  }
+});
+(args["kv"] || "")
+  .split(",")
+  .filter(name => name !== "")
+  .forEach(name => define(name, new KVNamespace(path.join(kvDir, name))));
+
+const script = fs.readFileSync(path.join(bundleDir, metadata.body_part), "utf8");
+vm.runInThisContext(script, { filename: "worker.js" });
+
+function dispatch(request) {
+  const event = new FetchEvent(request);
+  listeners.forEach(listener => listener.call(global, event));
+  if (!event._response) {
+    return Promise.reject(new Error("No fetch event handler responded to the request"));
+  }
+
+  return event._response.then(response => {
+    if (!(response instanceof Response)) {
+      throw new TypeError("The value passed to respondWith is not a Response");
+    }
+    return response;
+  });
+}
+
+const host = args["host"];
+const port = Number(args["port"]);
+
+http
+  .createServer((req, res) => {
+    const chunks = [];
+    req.on("data", chunk => chunks.push(chunk));
+    req.on("end", () => {
+      const url = `http://${req.headers.host || host + ":" + port}${req.url}`;
+      const hasBody = req.method !== "GET" && req.method !== "HEAD";
+      const request = new Request(url, {
+        method: req.method,
+        headers: fromNodeHeaders(req.headers),
+        body: hasBody ? Buffer.concat(chunks) : null
+      });
+
+      dispatch(request).then(
+        response => {
+          console.log(`${req.method} ${req.url} ${response.status}`);
+          res.writeHead(response.status, response.statusText, toNodeHeaders(response.headers));
+          res.end(req.method === "HEAD" ? undefined : response._body || undefined);
+        },
+        err => {
+          console.error(`${req.method} ${req.url} failed: ${err.stack || err}`);
+          res.writeHead(500, { "content-type": "text/plain" });
+          res.end(String(err.stack || err));
+        }
+      );
+    });
+  })
+  .listen(port, host, () => console.log(`Listening on http://${host}:${port}`));